@@ -1,4 +1,6 @@
-use crate::dsp::{fast_tanh, flush_denormals};
+use crate::dsp::{
+    ThreeBandEq, fast_tanh, flush_denormals, lcg_bipolar, quantize_to_scale, shape_macro,
+};
 use crate::params::{DrumSlotParams, MasterParams};
 use core::f32::consts::PI;
 
@@ -16,6 +18,46 @@ pub enum SlotType {
     Perc2,
 }
 
+impl SlotType {
+    /// Shortest effective decay (ms) per engine. Pitched, bass-heavy bodies get
+    /// clicky when cut short, so they floor higher than the noise engines:
+    /// kick 60, tom 40, snare 25, clap 20, perc 15, hats 5.
+    pub fn min_decay_ms(self) -> f32 {
+        match self {
+            SlotType::Kick => 60.0,
+            SlotType::Tom => 40.0,
+            SlotType::Snare => 25.0,
+            SlotType::Clap => 20.0,
+            SlotType::Perc1 | SlotType::Perc2 => 15.0,
+            SlotType::HatClosed | SlotType::HatOpen => 5.0,
+        }
+    }
+}
+
+impl SlotType {
+    /// Engines made of noise alone, which can render it as two streams.
+    pub fn is_noise_only(self) -> bool {
+        matches!(
+            self,
+            SlotType::Clap | SlotType::HatClosed | SlotType::HatOpen
+        )
+    }
+
+    /// Untransposed pitch (Hz) of each engine.
+    pub fn base_freq(self) -> f32 {
+        match self {
+            SlotType::Kick => 55.0,
+            SlotType::Snare => 180.0,
+            SlotType::Clap => 250.0,
+            SlotType::HatClosed => 8000.0,
+            SlotType::HatOpen => 7000.0,
+            SlotType::Tom => 140.0,
+            SlotType::Perc1 => 400.0,
+            SlotType::Perc2 => 700.0,
+        }
+    }
+}
+
 pub const SLOT_TYPES: [SlotType; N_SLOTS] = [
     SlotType::Kick,
     SlotType::Snare,
@@ -27,6 +69,155 @@ pub const SLOT_TYPES: [SlotType; N_SLOTS] = [
     SlotType::Perc2,
 ];
 
+/// Body oscillator shapes, indexed by the slot's `body_wave` param.
+pub const BODY_WAVES: [&str; 3] = ["Sine", "Triangle", "Saw"];
+
+/// Longest sample layer kept per slot, in time and in source samples (two
+/// seconds at 96 kHz), whichever is shorter; longer loads are truncated.
+pub const MAX_LAYER_SEC: f32 = 2.0;
+pub const MAX_LAYER_SAMPLES: usize = 192_000;
+
+/// A one-shot layered under a slot: (source sample rate, mono PCM).
+pub type SampleLayer = (f32, Vec<f32>);
+
+/// Noise filter modes, indexed by the slot's `noise_filter` param.
+pub const NOISE_FILTERS: [&str; 3] = ["High-pass", "Band-pass", "Low-pass"];
+
+/// Pitch scales, indexed by the slot's `scale` param. Chromatic leaves pitch free.
+pub const SCALES: [&str; 4] = ["Chromatic", "Major", "Minor", "Pentatonic"];
+
+/// Degrees of each scale in semitones above the slot's root.
+const SCALE_DEGREES: [&[f32]; 4] = [
+    &[],
+    &[0.0, 2.0, 4.0, 5.0, 7.0, 9.0, 11.0],
+    &[0.0, 2.0, 3.0, 5.0, 7.0, 8.0, 10.0],
+    &[0.0, 2.0, 4.0, 7.0, 9.0],
+];
+
+/// Humanize draw shapes, indexed by the master `humanize_dist` param.
+pub const HUMANIZE_DISTS: [&str; 2] = ["Uniform", "Gaussian"];
+
+/// Source of bipolar randoms for noise and humanize, advancing the given state.
+/// Slots use `lcg_bipolar`; tests can swap in a fixed sequence.
+pub type RandomSource = fn(&mut u32) -> f32;
+
+/// Harmonics used to build the non-sine bodies.
+const BODY_HARMONICS: usize = 8;
+
+/// Block-rate offsets from the global macros, applied on top of the slot params.
+#[derive(Clone, Copy)]
+pub struct MacroMods {
+    pub decay_mul: f32,
+    pub snap_offset: f32,
+    pub humanize_mul: f32,
+    /// LFO offsets on the tone macro and on pitch (semitones)
+    pub tone_offset: f32,
+    pub pitch_offset: f32,
+}
+
+impl Default for MacroMods {
+    fn default() -> Self {
+        Self {
+            decay_mul: 1.0,
+            snap_offset: 0.0,
+            humanize_mul: 1.0,
+            tone_offset: 0.0,
+            pitch_offset: 0.0,
+        }
+    }
+}
+
+impl MacroMods {
+    /// Tightness (-1 loose .. 1 tight): halves/doubles decays, shifts snap by
+    /// ±0.3 and scales humanize from 2× (loose) down to none (tight).
+    pub fn from_tightness(t: f32) -> Self {
+        let t = t.clamp(-1.0, 1.0);
+        Self {
+            decay_mul: 2.0f32.powf(-t),
+            snap_offset: 0.3 * t,
+            humanize_mul: 1.0 - t,
+            ..Self::default()
+        }
+    }
+}
+
+/// Outer bounds on any slot's pitch (Hz); each slot's Max Pitch sits inside them.
+const PITCH_MIN_HZ: f32 = 20.0;
+pub const PITCH_MAX_HZ: f32 = 12000.0;
+
+/// Envelope level below which a hit is considered finished.
+const ENV_OFF: f32 = 1e-4;
+
+/// Longest a hit can ring (s) with its decay at `decay_ms`: humanize (×1.5) and a
+/// loose tightness (×2) stretch it, and level-relative tails run it down to ENV_OFF / 10.
+pub fn max_voice_sec(decay_ms: f32) -> f32 {
+    let tau = decay_ms * 0.001 * 1.5 * 2.0;
+    tau * (10.0 / ENV_OFF).ln()
+}
+
+/// Length of a reversed swell, in decay time constants.
+const REVERSE_TAUS: f32 = 3.0;
+/// Fade at the end of a reversed swell, to avoid a click.
+const REVERSE_CUT_SEC: f32 = 0.002;
+
+/// Extra saturator gain at full Vel→Drive and full velocity.
+const VEL_DRIVE_MAX: f32 = 4.0;
+
+/// Measurement window and gain glide of the tone loudness compensation.
+const LOUDNESS_WINDOW_SEC: f32 = 0.02;
+const LOUDNESS_GLIDE_SEC: f32 = 0.01;
+
+/// Time constant of the transient-only reverb send gate.
+const SEND_GATE_SEC: f32 = 0.03;
+
+/// Time constant of the attack noise burst.
+const ATTACK_NOISE_SEC: f32 = 0.003;
+
+/// Wide Tone routing, from tone 0 to 1: noise cutoff -1 to +1 octave on top of the
+/// engine's own mapping, body/noise gains 1.3/0.7 to 0.7/1.3, saturator gain up to +50%.
+const WIDE_TONE_OCTAVES: f32 = 1.0;
+const WIDE_TONE_MIX: f32 = 0.3;
+const WIDE_TONE_DRIVE: f32 = 0.5;
+
+/// Noise cutoff sweep at full Env→Filter and full envelope.
+const ENV_FILTER_OCTAVES: f32 = 4.0;
+
+/// Upper limit of the "gentle" noise mode, so high-rate sessions don't carry
+/// ultrasonic noise into the saturators.
+const NOISE_CEILING_HZ: f32 = 18000.0;
+
+fn ceiling_alpha(sr: f32) -> f32 {
+    let fc = NOISE_CEILING_HZ.min(sr * 0.45);
+    1.0 - (-2.0 * PI * fc / sr).exp()
+}
+
+fn send_gate_coef(sr: f32) -> f32 {
+    (-1.0 / (SEND_GATE_SEC * sr)).exp()
+}
+
+fn attack_noise_coef(sr: f32) -> f32 {
+    (-1.0 / (ATTACK_NOISE_SEC * sr)).exp()
+}
+
+/// Hits per slot that Freeze Humanize captures and loops.
+const FREEZE_HITS: usize = 16;
+
+/// Offsets the attack burst's noise seed from the body's.
+const BURST_SEED: u32 = 0x27d4_eb2f;
+
+/// Offsets the right noise stream's seed from the left's.
+const NOISE_R_SEED: u32 = 0x5bd1_e995;
+
+/// A noise stream: its PRNG state and filter memory.
+#[derive(Clone, Copy, Default)]
+struct NoiseStream {
+    state: u32,
+    lp: f32,
+    bp: f32,
+    ceil_lp1: f32,
+    ceil_lp2: f32,
+}
+
 pub struct DrumSlot {
     pub kind: SlotType,
     pub sample_rate: f32,
@@ -34,21 +225,93 @@ pub struct DrumSlot {
     active: bool,
     env: f32,
     decay_coef: f32,
+    /// Envelope level at which this hit is considered finished
+    env_off: f32,
 
     velocity: f32,
+    /// Gain into the output saturator for this hit (1 = as before)
+    vel_drive: f32,
+    /// Tone offset for this hit, crossfaded between the soft and hard targets
+    tone_offset: f32,
+
+    // Smoothed tone/snap for the current sample
+    tone_now: f32,
+    snap_now: f32,
 
     // PRNG + simple noise state
     noise_state: u32,
+    rng: RandomSource,
     noise_lp: f32, // for simple one-pole HP (snare/hats/clap)
+    noise_bp: f32, // second pole for the band-pass noise mode
 
     // Pitched body
     osc_phase: f32,
+    sub_phase: f32,
     base_freq: f32,
+    /// This hit's Max Pitch: no body oscillator runs above it, sweeps included
+    max_freq: f32,
+
+    // Portamento: exponential glide of `base_freq` towards `glide_target`
+    glide_target: f32,
+    glide_step: f32,
+    glide_left: u32,
+    has_pitch: bool,
 
     // Per-hit humanization
     human_amp: f32,
     human_pitch: f32,     // in semitones
     human_decay_mul: f32, // 1 ± something
+
+    // Freeze humanize: the last FREEZE_HITS (amp, pitch, decay) draws, oldest at
+    // `human_head - human_len`; `human_replay` counts through them while frozen
+    human_ring: [(f32, f32, f32); FREEZE_HITS],
+    human_head: usize,
+    human_len: usize,
+    human_replay: Option<usize>,
+
+    eq: ThreeBandEq,
+
+    /// Global macro offsets, refreshed once per block
+    pub mods: MacroMods,
+
+    /// Eco mode for this hit: plain sine bodies
+    eco: bool,
+
+    // Gentle noise: two one-poles at NOISE_CEILING_HZ, independent of sample rate
+    gentle_noise: bool,
+    ceil_alpha: f32,
+    ceil_lp1: f32,
+    ceil_lp2: f32,
+
+    // Reverse envelope: swell for `rev_remaining` samples, then a short fade out
+    reverse: bool,
+    rev_remaining: u32,
+    cut_step: f32,
+
+    /// Read position in the sample layer, in source samples; `None` once it ends
+    layer_pos: Option<f32>,
+
+    // Tone loudness compensation: the raw engine RMS over the start of each hit
+    // steers a gain back towards the RMS measured when compensation was enabled
+    loud_ref: f32,
+    loud_sum: f32,
+    loud_count: u32,
+    loud_gain: f32,
+    loud_target: f32,
+
+    // Gated send: jumps to 1 on each hit and falls off over SEND_GATE_SEC
+    send_gate: f32,
+    send_gate_coef: f32,
+
+    // Attack noise: its own envelope, restarted on each hit, and its own PRNG state
+    // so the body's noise plays out the same with or without it
+    burst_env: f32,
+    burst_coef: f32,
+    burst_state: u32,
+
+    // Stereo noise: the right channel's stream, swapped in for a second render
+    noise_r: NoiseStream,
+    eq_r: ThreeBandEq,
 }
 
 impl DrumSlot {
@@ -59,31 +322,167 @@ impl DrumSlot {
             active: false,
             env: 0.0,
             decay_coef: 0.999,
+            env_off: ENV_OFF,
             velocity: 0.0,
+            vel_drive: 1.0,
+            tone_offset: 0.0,
+            tone_now: 0.0,
+            snap_now: 0.0,
             noise_state: 1,
+            rng: lcg_bipolar,
             noise_lp: 0.0,
+            noise_bp: 0.0,
             osc_phase: 0.0,
+            sub_phase: 0.0,
             base_freq: 100.0,
+            max_freq: PITCH_MAX_HZ,
+            glide_target: 100.0,
+            glide_step: 1.0,
+            glide_left: 0,
+            has_pitch: false,
             human_amp: 1.0,
             human_pitch: 0.0,
             human_decay_mul: 1.0,
+            human_ring: [(1.0, 0.0, 1.0); FREEZE_HITS],
+            human_head: 0,
+            human_len: 0,
+            human_replay: None,
+            eq: ThreeBandEq::new(sample_rate.max(1.0)),
+            mods: MacroMods::default(),
+            eco: false,
+            gentle_noise: false,
+            ceil_alpha: ceiling_alpha(sample_rate.max(1.0)),
+            ceil_lp1: 0.0,
+            ceil_lp2: 0.0,
+            reverse: false,
+            rev_remaining: 0,
+            cut_step: 1.0,
+            layer_pos: None,
+            loud_ref: 0.0,
+            loud_sum: 0.0,
+            loud_count: 0,
+            loud_gain: 1.0,
+            loud_target: 1.0,
+            send_gate: 0.0,
+            send_gate_coef: send_gate_coef(sample_rate.max(1.0)),
+            burst_env: 0.0,
+            burst_coef: attack_noise_coef(sample_rate.max(1.0)),
+            burst_state: 1 ^ BURST_SEED,
+            noise_r: NoiseStream {
+                state: 1 ^ NOISE_R_SEED,
+                ..NoiseStream::default()
+            },
+            eq_r: ThreeBandEq::new(sample_rate.max(1.0)),
         }
     }
 
     pub fn set_sample_rate(&mut self, sr: f32) {
         self.sample_rate = sr.max(1.0);
+        self.ceil_alpha = ceiling_alpha(self.sample_rate);
+        self.eq.set_sample_rate(self.sample_rate);
+        self.eq_r.set_sample_rate(self.sample_rate);
+        self.send_gate_coef = send_gate_coef(self.sample_rate);
+        self.burst_coef = attack_noise_coef(self.sample_rate);
+    }
+
+    /// Reverb send scale for slots that only send their attack.
+    pub fn send_gate(&self) -> f32 {
+        self.send_gate
+    }
+
+    /// Whether a hit is still sounding.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Current envelope level of the sounding hit (0..1).
+    pub fn envelope(&self) -> f32 {
+        self.env
+    }
+
+    /// Restart the humanize/noise RNG from `seed`.
+    pub fn seed(&mut self, seed: u32) {
+        self.noise_state = seed;
+        self.noise_r.state = seed ^ NOISE_R_SEED;
+        self.burst_state = seed ^ BURST_SEED;
+    }
+
+    /// Time constant of the current hit's decay, in ms.
+    #[cfg(test)]
+    pub fn decay_ms(&self) -> f32 {
+        -1000.0 / (self.decay_coef.ln() * self.sample_rate)
+    }
+
+    /// Replace the humanize/noise RNG; the state and seeding stay as they are.
+    #[cfg(test)]
+    pub fn set_random_source(&mut self, rng: RandomSource) {
+        self.rng = rng;
     }
 
     /// Trigger a new drum hit for this slot, using slot/master params for humanization & decay.
-    pub fn trigger(&mut self, velocity: f32, slot_params: &DrumSlotParams, master: &MasterParams) {
-        self.active = true;
-        self.env = 1.0;
-        self.noise_lp = 0.0;
+    /// `note_pitch` is a per-note offset in semitones on top of the slot's tuning.
+    pub fn trigger(
+        &mut self,
+        velocity: f32,
+        note_pitch: f32,
+        slot_params: &DrumSlotParams,
+        master: &MasterParams,
+    ) {
+        let decay_ms = slot_params.decay.smoothed.previous_value();
+        self.trigger_with_decay(velocity, note_pitch, slot_params, master, decay_ms);
+    }
+
+    /// Like `trigger`, but with the decay (ms) supplied by the caller.
+    pub fn trigger_with_decay(
+        &mut self,
+        velocity: f32,
+        note_pitch: f32,
+        slot_params: &DrumSlotParams,
+        master: &MasterParams,
+        decay_ms: f32,
+    ) {
+        // Pad range: remap 0..1 onto the slot's floor..ceiling before anything reads it
+        let floor = slot_params.vel_floor.value();
+        let ceiling = slot_params.vel_ceiling.value().max(floor);
+        let velocity = floor + (ceiling - floor) * velocity.clamp(0.0, 1.0);
 
         // Velocity curve
-        let v_curve = master.velocity_curve.value().clamp(0.0, 1.0);
+        let v_curve = master
+            .velocity_curve
+            .smoothed
+            .previous_value()
+            .clamp(0.0, 1.0);
         let shape = 0.5 + v_curve; // 0.5..1.5
-        self.velocity = velocity.clamp(0.0, 1.0).powf(shape);
+        let curved = velocity.clamp(0.0, 1.0).powf(shape);
+
+        // Loudest wins: a ghost note can't cut off a hit that's still louder
+        if slot_params.loudest_wins.value() && self.active && curved < self.velocity * self.env {
+            return;
+        }
+
+        // Rolls: a retrigger can keep the warmed-up noise filter instead of
+        // restarting it from zero, which dips the level for a moment
+        if !(self.active && slot_params.smooth_rolls.value()) {
+            self.noise_lp = 0.0;
+            self.noise_bp = 0.0;
+            self.noise_r.lp = 0.0;
+            self.noise_r.bp = 0.0;
+        }
+
+        self.active = true;
+
+        self.gentle_noise = master.gentle_noise.value();
+        self.eco = master.eco.value();
+        self.velocity = curved;
+
+        // Vel→Drive: squared so soft hits stay clean while hard hits push the saturator
+        let v = velocity.clamp(0.0, 1.0);
+        self.vel_drive = 1.0 + slot_params.vel_drive.value() * v * v * VEL_DRIVE_MAX;
+
+        // Velocity layers: soft hits take the soft tone, hard hits the hard one
+        let soft = slot_params.tone_soft.value();
+        let hard = slot_params.tone_hard.value();
+        self.tone_offset = soft + (hard - soft) * velocity.clamp(0.0, 1.0);
 
         // Reseed RNG
         self.noise_state = self
@@ -92,92 +491,385 @@ impl DrumSlot {
             .wrapping_add(1013904223);
 
         // Humanization
-        let h = slot_params.humanize.value();
-        if h > 0.0 {
-            let r1 = self.random_bipolar();
-            let r2 = self.random_bipolar();
-            let r3 = self.random_bipolar();
-            self.human_amp = 1.0 + r1 * 0.15 * h; // ±15%
-            self.human_pitch = r2 * 3.0 * h; // ±3 semitones
-            self.human_decay_mul = 1.0 + r3 * 0.5 * h; // ±50%
+        if master.freeze_humanize.value() && self.human_len > 0 {
+            self.replay_humanize();
         } else {
-            self.human_amp = 1.0;
-            self.human_pitch = 0.0;
-            self.human_decay_mul = 1.0;
+            self.human_replay = None;
+            let h = (slot_params.humanize.smoothed.previous_value() * self.mods.humanize_mul)
+                .clamp(0.0, 1.0);
+            if h > 0.0 {
+                let gaussian = master.humanize_dist.value() == 1;
+                let r1 = self.humanize_draw(gaussian);
+                let r2 = self.humanize_draw(gaussian);
+                let r3 = self.humanize_draw(gaussian);
+                self.human_amp = 1.0 + r1 * 0.15 * h; // ±15%
+                self.human_pitch = r2 * 3.0 * h; // ±3 semitones
+                self.human_decay_mul = 1.0 + r3 * 0.5 * h; // ±50%
+            } else {
+                self.human_amp = 1.0;
+                self.human_pitch = 0.0;
+                self.human_decay_mul = 1.0;
+            }
+            self.capture_humanize();
         }
 
+        // Level-relative cutoff: end the voice at a fixed output level rather than a
+        // fixed envelope level, so loud hits ring on and quiet ones stop sooner
+        self.env_off = if master.relative_tails.value() {
+            let peak = self.velocity * self.human_amp * slot_params.level.smoothed.previous_value();
+            (ENV_OFF / peak.max(1e-3)).clamp(ENV_OFF * 0.1, 0.1)
+        } else {
+            ENV_OFF
+        };
+        // Start offset: begin part-way down the decay, always well above this hit's
+        // cutoff (at most 0.1, so the range never inverts)
+        self.env = slot_params.start.value().clamp(self.env_off * 10.0, 1.0);
+
+        let pitch_offset = slot_params.pitch.smoothed.previous_value()
+            + master.kit_pitch.smoothed.previous_value()
+            + note_pitch
+            + self.human_pitch
+            + self.mods.pitch_offset;
+        // Keep the transposed pitch on the chosen scale, rooted at the engine's base pitch
+        let scale = SCALE_DEGREES
+            .get(slot_params.scale.value() as usize)
+            .copied()
+            .unwrap_or_default();
+        let pitch_offset = quantize_to_scale(pitch_offset, scale);
+
         // Exponential decay from ms param
-        let decay_ms = slot_params.decay.value().max(5.0);
-        let decay_sec = (decay_ms / 1000.0) * self.human_decay_mul;
-        let tau = decay_sec.max(0.001);
+        // Floored per engine, including after humanization
+        let min_decay_sec = self.kind.min_decay_ms() / 1000.0;
+        let mut decay_sec = (decay_ms / 1000.0) * self.human_decay_mul * self.mods.decay_mul;
+        // Decay link: tuned up rings shorter, like a tighter head (an octave halves it at 1)
+        let link = slot_params.decay_link.value();
+        if link > 0.0 {
+            decay_sec *= 2.0f32.powf(-link * pitch_offset / 12.0);
+        }
+        let tau = decay_sec.max(min_decay_sec);
         self.decay_coef = (-1.0 / (tau * self.sample_rate)).exp();
 
-        // Base pitch per slot
-        let base = match self.kind {
-            SlotType::Kick => 55.0,
-            SlotType::Snare => 180.0,
-            SlotType::Clap => 250.0,
-            SlotType::HatClosed => 8000.0,
-            SlotType::HatOpen => 7000.0,
-            SlotType::Tom => 140.0,
-            SlotType::Perc1 => 400.0,
-            SlotType::Perc2 => 700.0,
-        };
+        // Reverse: mirror the first three time constants of the decay as a swell
+        self.reverse = slot_params.reverse.value();
+        if self.reverse {
+            self.rev_remaining = (REVERSE_TAUS * tau * self.sample_rate) as u32;
+            self.env = (-REVERSE_TAUS).exp();
+            self.cut_step = 1.0 / (REVERSE_CUT_SEC * self.sample_rate).max(1.0);
+        }
+
+        let base = self.kind.base_freq();
+
+        // Concert pitch: every engine's base pitch assumes A4 = 440 Hz
+        let ratio = 2.0f32.powf(pitch_offset / 12.0) * master.tuning.value() / 440.0;
+        let ceiling = slot_params
+            .max_pitch
+            .value()
+            .clamp(PITCH_MIN_HZ, PITCH_MAX_HZ);
+        let target = (base * ratio).clamp(PITCH_MIN_HZ, ceiling);
+        self.max_freq = ceiling;
 
-        let pitch_offset = slot_params.pitch.value() + master.kit_pitch.value() + self.human_pitch;
-        let ratio = 2.0f32.powf(pitch_offset / 12.0);
-        self.base_freq = (base * ratio).clamp(20.0, 12000.0);
-        self.osc_phase = 0.0;
+        // Glide from wherever the previous hit's pitch currently is
+        let glide_samples = (slot_params.glide.value() * 0.001 * self.sample_rate) as u32;
+        if glide_samples > 0 && self.has_pitch {
+            self.glide_target = target;
+            self.glide_step = (target / self.base_freq).powf(1.0 / glide_samples as f32);
+            self.glide_left = glide_samples;
+        } else {
+            self.base_freq = target;
+            self.glide_left = 0;
+        }
+        self.has_pitch = true;
+        // Phase random: start the body anywhere up to amount × a full cycle in
+        let phase_random = slot_params.phase_random.value();
+        self.osc_phase = if phase_random > 0.0 {
+            (self.random_bipolar() * 0.5 + 0.5) * phase_random * 2.0 * PI
+        } else {
+            0.0
+        };
+        self.sub_phase = 0.0;
+        self.loud_sum = 0.0;
+        self.loud_count = 0;
+        self.layer_pos = Some(0.0);
+        self.send_gate = 1.0;
+        self.burst_env = 1.0;
     }
 
-    /// Render one sample for this slot.
-    pub fn process(&mut self, slot_params: &DrumSlotParams, master: &MasterParams) -> f32 {
+    /// Render one left/right sample pair for this slot, before panning. Both sides
+    /// are equal unless the slot's stereo noise is up. `layer` is the slot's sample
+    /// layer, if any.
+    pub fn process(
+        &mut self,
+        slot_params: &DrumSlotParams,
+        master: &MasterParams,
+        layer: Option<(f32, &[f32])>,
+    ) -> (f32, f32) {
+        // Step the smoothers every sample, sounding or not, so they never lag behind.
+        // Decay, pitch and humanize are only read at trigger time, through
+        // `previous_value`, so they glide in wall-clock time too.
+        self.tone_now = slot_params.tone.smoothed.next();
+        self.snap_now = slot_params.snap.smoothed.next();
+        slot_params.decay.smoothed.next();
+        slot_params.pitch.smoothed.next();
+        slot_params.humanize.smoothed.next();
+
         if !self.active {
-            return 0.0;
+            return (0.0, 0.0);
         }
 
-        self.env *= self.decay_coef;
-        if self.env < 1e-4 {
+        // Eco: no sample layer, stereo noise or EQ, whatever the slot asks for
+        let eco = master.eco.value();
+        let layer = if eco { None } else { layer };
+
+        self.send_gate *= self.send_gate_coef;
+
+        if !self.advance_env() {
             self.env = 0.0;
-            self.active = false;
-            return 0.0;
+            // A sample layer outlasting the synth voice plays on to its end
+            if layer.is_none() || self.layer_pos.is_none() {
+                self.active = false;
+                return (0.0, 0.0);
+            }
+        }
+
+        if self.glide_left > 0 {
+            self.glide_left -= 1;
+            self.base_freq = if self.glide_left == 0 {
+                self.glide_target
+            } else {
+                self.base_freq * self.glide_step
+            };
         }
 
         let env = self.env;
-        let sample = match self.kind {
-            SlotType::Kick => self.render_kick(env, slot_params),
-            SlotType::Snare => self.render_snare(env, slot_params),
-            SlotType::Clap => self.render_clap(env, slot_params),
-            SlotType::HatClosed => self.render_hat_closed(env, slot_params),
-            SlotType::HatOpen => self.render_hat_open(env, slot_params),
-            SlotType::Tom => self.render_tom(env, slot_params),
-            SlotType::Perc1 => self.render_perc1(env, slot_params),
-            SlotType::Perc2 => self.render_perc2(env, slot_params),
+        let sample = self.render(env, slot_params);
+
+        // Stereo noise: render the noise engines again from the right-hand stream
+        let stereo = slot_params.stereo.value().clamp(0.0, 1.0);
+        let sample_r = if stereo > 0.0 && !eco && self.kind.is_noise_only() {
+            self.swap_noise_stream();
+            let r = self.render(env, slot_params);
+            self.swap_noise_stream();
+            Some(r)
+        } else {
+            None
+        };
+
+        let mut sample = if slot_params.tone_comp.value() {
+            self.compensate_loudness(sample)
+        } else {
+            self.loud_ref = 0.0;
+            self.loud_gain = 1.0;
+            self.loud_target = 1.0;
+            sample
         };
 
+        // Attack noise: unfiltered grit over the first few ms, apart from the body's own
+        // noise. Drawn raw from its own stream, so Gentle Noise's ceiling filter neither
+        // dulls the burst nor has its state pushed around by it, and the body's noise
+        // doesn't shift
+        let attack_noise = slot_params.attack_noise.value();
+        if attack_noise > 0.0
+            && matches!(self.kind, SlotType::Kick | SlotType::Snare | SlotType::Tom)
+        {
+            let noise = (self.rng)(&mut self.burst_state);
+            sample += noise * 0.7 * attack_noise * self.burst_env;
+        }
+        self.burst_env *= self.burst_coef;
+
         // Global per-hit scaling
-        let mut out = sample * env * self.velocity * self.human_amp;
+        let gain = env * self.velocity * self.human_amp;
+
+        let layer_out = match layer {
+            Some(layer) => {
+                let level = slot_params.layer_level.value();
+                self.next_layer_sample(layer) * level * self.velocity
+            }
+            None => 0.0,
+        };
 
         // Simple master drive is handled later; here just a gentle per-slot saturator
-        out = fast_tanh(out);
-        flush_denormals(out)
+        let drive = if slot_params.clean.value() {
+            None
+        } else if slot_params.wide_tone.value() {
+            Some(self.vel_drive * (1.0 + WIDE_TONE_DRIVE * self.shaped_tone(slot_params)))
+        } else {
+            Some(self.vel_drive)
+        };
+        let finish = |raw: f32| {
+            let out = raw * gain + layer_out;
+            drive.map_or(out, |drive| fast_tanh(out * drive))
+        };
+
+        let (low, mid, high) = if eco {
+            (0.0, 0.0, 0.0)
+        } else {
+            (
+                slot_params.eq_low.value(),
+                slot_params.eq_mid.value(),
+                slot_params.eq_high.value(),
+            )
+        };
+        let out = self.eq.process(finish(sample), low, mid, high);
+        let out_r = match sample_r {
+            // The right side follows the left's loudness compensation gain
+            Some(raw) => {
+                let r = self
+                    .eq_r
+                    .process(finish(raw * self.loud_gain), low, mid, high);
+                out + (r - out) * stereo
+            }
+            None => out,
+        };
+        (flush_denormals(out), flush_denormals(out_r))
+    }
+
+    /// One sample of the slot's engine, before the per-hit gain.
+    #[inline]
+    fn render(&mut self, env: f32, p: &DrumSlotParams) -> f32 {
+        match self.kind {
+            SlotType::Kick => self.render_kick(env, p),
+            SlotType::Snare => self.render_snare(env, p),
+            SlotType::Clap => self.render_clap(env, p),
+            SlotType::HatClosed => self.render_hat_closed(env, p),
+            SlotType::HatOpen => self.render_hat_open(env, p),
+            SlotType::Tom => self.render_tom(env, p),
+            SlotType::Perc1 => self.render_perc1(env, p),
+            SlotType::Perc2 => self.render_perc2(env, p),
+        }
+    }
+
+    /// Trade the live noise stream for the right-hand one, and back on the second call.
+    #[inline]
+    fn swap_noise_stream(&mut self) {
+        let r = &mut self.noise_r;
+        core::mem::swap(&mut self.noise_state, &mut r.state);
+        core::mem::swap(&mut self.noise_lp, &mut r.lp);
+        core::mem::swap(&mut self.noise_bp, &mut r.bp);
+        core::mem::swap(&mut self.ceil_lp1, &mut r.ceil_lp1);
+        core::mem::swap(&mut self.ceil_lp2, &mut r.ceil_lp2);
+    }
+
+    /// Step the amplitude envelope; returns false once the hit has finished.
+    #[inline]
+    fn advance_env(&mut self) -> bool {
+        if !self.reverse {
+            self.env *= self.decay_coef;
+            return self.env >= self.env_off;
+        }
+
+        if self.rev_remaining > 0 {
+            self.rev_remaining -= 1;
+            self.env = (self.env / self.decay_coef).min(1.0);
+        } else {
+            self.env -= self.cut_step;
+        }
+        self.env > 0.0
+    }
+
+    /// Next sample of the layer, linearly interpolated to the engine rate.
+    #[inline]
+    fn next_layer_sample(&mut self, (rate, pcm): (f32, &[f32])) -> f32 {
+        let Some(pos) = self.layer_pos else {
+            return 0.0;
+        };
+        let i = pos as usize;
+        if i + 1 >= pcm.len() {
+            self.layer_pos = None;
+            return 0.0;
+        }
+        let frac = pos - i as f32;
+        self.layer_pos = Some(pos + rate / self.sample_rate);
+        pcm[i] + (pcm[i + 1] - pcm[i]) * frac
+    }
+
+    /// Hold the raw engine level steady as tone changes. The first hit measured
+    /// sets the reference; later hits glide their gain towards it.
+    #[inline]
+    fn compensate_loudness(&mut self, raw: f32) -> f32 {
+        let window = (LOUDNESS_WINDOW_SEC * self.sample_rate) as u32;
+        if self.loud_count < window {
+            self.loud_sum += raw * raw;
+            self.loud_count += 1;
+            if self.loud_count == window {
+                let rms = (self.loud_sum / window as f32).sqrt();
+                if rms > 1e-6 {
+                    if self.loud_ref == 0.0 {
+                        self.loud_ref = rms;
+                    }
+                    self.loud_target = (self.loud_ref / rms).clamp(0.25, 4.0);
+                }
+            }
+        }
+        let alpha = 1.0 / (LOUDNESS_GLIDE_SEC * self.sample_rate).max(1.0);
+        self.loud_gain += alpha * (self.loud_target - self.loud_gain);
+        raw * self.loud_gain
+    }
+
+    /// Tone macro plus this hit's velocity-layer offset, after the response curve.
+    #[inline]
+    fn shaped_tone(&self, p: &DrumSlotParams) -> f32 {
+        shape_macro(
+            self.tone_now + self.tone_offset + self.mods.tone_offset,
+            p.tone_curve.value(),
+        )
+    }
+
+    /// Body and noise gains for the Wide Tone routing: tone trades body for noise.
+    #[inline]
+    fn tone_mix(&self, p: &DrumSlotParams) -> (f32, f32) {
+        if !p.wide_tone.value() {
+            return (1.0, 1.0);
+        }
+        let t = (self.shaped_tone(p) * 2.0 - 1.0) * WIDE_TONE_MIX;
+        (1.0 - t, 1.0 + t)
+    }
+
+    /// Snap macro including the global offset.
+    #[inline]
+    fn snap(&self) -> f32 {
+        (self.snap_now + self.mods.snap_offset).clamp(0.0, 1.0)
     }
 
     #[inline]
     fn random_bipolar(&mut self) -> f32 {
-        self.noise_state = self
-            .noise_state
-            .wrapping_mul(1664525)
-            .wrapping_add(1013904223);
+        (self.rng)(&mut self.noise_state)
+    }
+
+    /// Remember this hit's humanize values for Freeze Humanize.
+    #[inline]
+    fn capture_humanize(&mut self) {
+        self.human_ring[self.human_head] = (self.human_amp, self.human_pitch, self.human_decay_mul);
+        self.human_head = (self.human_head + 1) % FREEZE_HITS;
+        self.human_len = (self.human_len + 1).min(FREEZE_HITS);
+    }
 
-        let bits = 0x3F800000 | (self.noise_state >> 9);
-        let f = f32::from_bits(bits) - 1.0;
-        f * 2.0 - 1.0
+    /// Take the next captured humanize values, oldest first, looping.
+    #[inline]
+    fn replay_humanize(&mut self) {
+        let k = self.human_replay.unwrap_or(0);
+        let i = (self.human_head + FREEZE_HITS - self.human_len + k) % FREEZE_HITS;
+        (self.human_amp, self.human_pitch, self.human_decay_mul) = self.human_ring[i];
+        self.human_replay = Some((k + 1) % self.human_len);
+    }
+
+    /// One humanize draw in -1..1: flat, or bell-shaped as the mean of three draws.
+    #[inline]
+    fn humanize_draw(&mut self, gaussian: bool) -> f32 {
+        if !gaussian {
+            return self.random_bipolar();
+        }
+        (self.random_bipolar() + self.random_bipolar() + self.random_bipolar()) / 3.0
     }
 
     #[inline]
     fn next_noise(&mut self) -> f32 {
-        self.random_bipolar() * 0.7
+        let n = self.random_bipolar() * 0.7;
+        if !self.gentle_noise {
+            return n;
+        }
+        self.ceil_lp1 += self.ceil_alpha * (n - self.ceil_lp1);
+        self.ceil_lp2 += self.ceil_alpha * (self.ceil_lp1 - self.ceil_lp2);
+        self.ceil_lp2
     }
 
     #[inline]
@@ -190,61 +882,139 @@ impl DrumSlot {
         self.osc_phase.sin()
     }
 
-    // equal-power-ish LP-based highpass on noise: returns HP component
+    /// Pitched body in the slot's chosen shape. Triangle and saw are summed from
+    /// a few harmonics, skipping any above ~0.45 × sr so high pitches don't alias.
     #[inline]
-    fn hp_noise(&mut self, noise: f32, cutoff_hz: f32) -> f32 {
+    fn next_body(&mut self, freq: f32, p: &DrumSlotParams) -> f32 {
+        // Sweeps and the perc engines' ratios ride above the base pitch: hold them
+        // to the ceiling too
+        let freq = freq.min(self.max_freq);
+        let fundamental = self.next_sine(freq);
+        let wave = p.body_wave.value();
+        if wave == 0 || self.eco {
+            return fundamental;
+        }
+
+        let max_harmonic = (self.sample_rate * 0.45 / freq.max(1.0)) as usize;
+        let mut y = fundamental;
+        for k in 2..=BODY_HARMONICS.min(max_harmonic) {
+            let h = (self.osc_phase * k as f32).sin() / k as f32;
+            y += match (wave, k % 4) {
+                // Triangle: odd harmonics at 1/k², alternating sign
+                (1, 1) => h / k as f32,
+                (1, 3) => -h / k as f32,
+                (1, _) => 0.0,
+                // Saw: every harmonic at 1/k
+                _ => h,
+            };
+        }
+        // Keep the peak near a plain sine's
+        if wave == 1 { y * 0.8 } else { y * 0.55 }
+    }
+
+    // One-pole filter on noise, in the slot's chosen mode (HP by default)
+    #[inline]
+    fn filter_noise(&mut self, noise: f32, cutoff_hz: f32, p: &DrumSlotParams) -> f32 {
+        // Env→Filter: the amplitude envelope sweeps the cutoff by up to ±ENV_FILTER_OCTAVES
+        let env_mod = p.env_filter.value();
+        let cutoff_hz = if env_mod != 0.0 {
+            cutoff_hz * 2.0f32.powf(env_mod * self.env * ENV_FILTER_OCTAVES)
+        } else {
+            cutoff_hz
+        };
+        // Wide Tone: the macro also opens the cutoff across its own octave range
+        let cutoff_hz = if p.wide_tone.value() {
+            cutoff_hz * 2.0f32.powf((self.shaped_tone(p) * 2.0 - 1.0) * WIDE_TONE_OCTAVES)
+        } else {
+            cutoff_hz
+        };
+        // Keytracking: the cutoff follows this hit's pitch, kit pitch included
+        let cutoff_hz = if p.key_track.value() {
+            cutoff_hz * self.base_freq / self.kind.base_freq()
+        } else {
+            cutoff_hz
+        };
         let fc = cutoff_hz.clamp(200.0, self.sample_rate * 0.45);
         let alpha = 1.0 - (-2.0 * PI * fc / self.sample_rate).exp();
         self.noise_lp += alpha * (noise - self.noise_lp);
-        noise - self.noise_lp
+        let hp = noise - self.noise_lp;
+        match p.noise_filter.value() {
+            // Low-pass: the part the high-pass throws away
+            2 => self.noise_lp,
+            // Band-pass: the high-pass, low-passed again at the same corner
+            1 => {
+                self.noise_bp += alpha * (hp - self.noise_bp);
+                2.0 * self.noise_bp
+            }
+            _ => hp,
+        }
     }
 
     // Slot-specific engines
 
     fn render_kick(&mut self, env: f32, p: &DrumSlotParams) -> f32 {
-        let tone = p.tone.value(); // 0..1
-        let snap = p.snap.value();
+        let tone = self.shaped_tone(p); // 0..1
+        let snap = self.snap();
 
         // Pitch sweep: more tone -> deeper sweep
         let sweep_semitones = 30.0 * (0.3 + 0.7 * tone);
         let sweep = sweep_semitones * env * env;
-        let freq = self.base_freq * 2.0f32.powf(sweep / 12.0);
+        let freq = (self.base_freq * 2.0f32.powf(sweep / 12.0)).min(self.max_freq);
 
-        let mut body = self.next_sine(freq);
-        body = fast_tanh(body * (1.0 + 3.0 * snap)); // more snap => more distortion
+        // Snap drives both the body distortion and the click; Body Drive and Click
+        // offset each side from snap so they can be set apart
+        let drive = (snap + p.body_drive.value()).clamp(0.0, 1.0);
+        let click_amt = (snap + p.click.value()).clamp(0.0, 1.0);
+
+        let mut body = self.next_body(freq, p);
+        body = fast_tanh(body * (1.0 + 3.0 * drive)); // more drive => more distortion
 
         // Attack click: short, bright noise
         let click_env = env.powf(0.3);
         let noise = self.next_noise();
-        let click = self.hp_noise(noise, 4000.0 + 4000.0 * tone) * snap * click_env;
+        let click = self.filter_noise(noise, 4000.0 + 4000.0 * tone, p) * click_amt * click_env;
+
+        // Sub an octave down, tracking the sweep but never below 20 Hz
+        let sub_amt = p.sub.value();
+        let mut sub = 0.0;
+        if sub_amt > 0.0 {
+            let sub_freq = (freq * 0.5).max(20.0);
+            self.sub_phase += 2.0 * PI * sub_freq / self.sample_rate;
+            if self.sub_phase > 2.0 * PI {
+                self.sub_phase -= 2.0 * PI;
+            }
+            sub = self.sub_phase.sin() * sub_amt;
+        }
 
-        body * 0.9 + click * 0.4
+        let (body_mix, noise_mix) = self.tone_mix(p);
+        body * 0.9 * body_mix + sub * 0.9 + click * 0.4 * noise_mix
     }
 
     fn render_snare(&mut self, _env: f32, p: &DrumSlotParams) -> f32 {
-        let tone = p.tone.value();
-        let snap = p.snap.value();
+        let tone = self.shaped_tone(p);
+        let snap = self.snap();
 
         // Pitched body around base_freq
-        let body = self.next_sine(self.base_freq);
+        let body = self.next_body(self.base_freq, p);
 
         // Bright noise band
         let noise = self.next_noise();
-        let noise_hp = self.hp_noise(noise, 2000.0 + 6000.0 * tone);
+        let noise_hp = self.filter_noise(noise, 2000.0 + 6000.0 * tone, p);
 
-        let body_mix = 0.4 * (1.0 - tone); // darker tone -> more body
-        let noise_mix = 0.8 + 0.4 * snap; // snap -> more noise
+        let (wide_body, wide_noise) = self.tone_mix(p);
+        let body_mix = 0.4 * (1.0 - tone) * wide_body; // darker tone -> more body
+        let noise_mix = (0.8 + 0.4 * snap) * wide_noise; // snap -> more noise
 
         body * body_mix + noise_hp * noise_mix
     }
 
     fn render_clap(&mut self, env: f32, p: &DrumSlotParams) -> f32 {
-        let tone = p.tone.value();
-        let snap = p.snap.value();
+        let tone = self.shaped_tone(p);
+        let snap = self.snap();
 
         let noise = self.next_noise();
         // Medium band noise
-        let band = self.hp_noise(noise, 800.0 + 1200.0 * (1.0 - tone));
+        let band = self.filter_noise(noise, 800.0 + 1200.0 * (1.0 - tone), p);
 
         // Faux "multi-burst": emphasize early envelope region
         let burst = (env.powf(0.3) * (1.0 + 0.6 * snap)).min(1.5);
@@ -252,11 +1022,11 @@ impl DrumSlot {
     }
 
     fn render_hat_closed(&mut self, env: f32, p: &DrumSlotParams) -> f32 {
-        let tone = p.tone.value();
-        let snap = p.snap.value();
+        let tone = self.shaped_tone(p);
+        let snap = self.snap();
 
         let noise = self.next_noise();
-        let noise_hp = self.hp_noise(noise, 6000.0 + 6000.0 * tone);
+        let noise_hp = self.filter_noise(noise, 6000.0 + 6000.0 * tone, p);
 
         // Very snappy decay shape
         let shape = env.powf(2.5 - 1.5 * snap);
@@ -265,11 +1035,11 @@ impl DrumSlot {
     }
 
     fn render_hat_open(&mut self, env: f32, p: &DrumSlotParams) -> f32 {
-        let tone = p.tone.value();
-        let snap = p.snap.value();
+        let tone = self.shaped_tone(p);
+        let snap = self.snap();
 
         let noise = self.next_noise();
-        let noise_hp = self.hp_noise(noise, 5000.0 + 5000.0 * tone);
+        let noise_hp = self.filter_noise(noise, 5000.0 + 5000.0 * tone, p);
 
         let shape = env.powf(1.2 + 0.8 * snap); // more snap -> slightly faster
 
@@ -277,36 +1047,1122 @@ impl DrumSlot {
     }
 
     fn render_tom(&mut self, _env: f32, p: &DrumSlotParams) -> f32 {
-        let tone = p.tone.value();
+        let tone = self.shaped_tone(p);
 
-        let body = self.next_sine(self.base_freq);
+        let body = self.next_body(self.base_freq, p);
         let noise = self.next_noise();
-        let noise_hp = self.hp_noise(noise, 1500.0 + 3000.0 * tone);
+        let noise_hp = self.filter_noise(noise, 1500.0 + 3000.0 * tone, p);
 
-        body * 0.9 + noise_hp * 0.3
+        let (body_mix, noise_mix) = self.tone_mix(p);
+        body * 0.9 * body_mix + noise_hp * 0.3 * noise_mix
     }
 
     fn render_perc1(&mut self, env: f32, p: &DrumSlotParams) -> f32 {
-        let tone = p.tone.value();
+        let tone = self.shaped_tone(p);
 
         let noise = self.next_noise();
-        let noise_hp = self.hp_noise(noise, 2500.0 + 6000.0 * tone);
+        let noise_hp = self.filter_noise(noise, 2500.0 + 6000.0 * tone, p);
 
-        // Slight metallic ring via a pitched element
-        let body = self.next_sine(self.base_freq * (1.5 + 0.5 * tone));
+        // Metallic ring via a pitched element, traded against the noise
+        let body = self.next_body(self.base_freq * (1.5 + 0.5 * tone), p);
+        let ring = p.ring.value().clamp(0.0, 1.0);
 
         let burst = env.powf(0.7);
-        body * 0.3 + noise_hp * 0.9 * burst
+        let (body_mix, noise_mix) = self.tone_mix(p);
+        body * 1.2 * ring * body_mix + noise_hp * 1.2 * (1.0 - ring) * burst * noise_mix
     }
 
     fn render_perc2(&mut self, env: f32, p: &DrumSlotParams) -> f32 {
-        let tone = p.tone.value();
+        let tone = self.shaped_tone(p);
 
-        let body = self.next_sine(self.base_freq * (1.0 + tone));
+        let body = self.next_body(self.base_freq * (1.0 + tone), p);
         let noise = self.next_noise();
-        let noise_hp = self.hp_noise(noise, 2000.0 + 5000.0 * tone);
+        let noise_hp = self.filter_noise(noise, 2000.0 + 5000.0 * tone, p);
 
         let shape = env.powf(0.9);
-        body * 0.6 * shape + noise_hp * 0.5 * shape
+        let (body_mix, noise_mix) = self.tone_mix(p);
+        body * 0.6 * shape * body_mix + noise_hp * 0.5 * shape * noise_mix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::analysis;
+    use crate::params::settle_smoothers;
+    use nih_plug::prelude::*;
+
+    const SR: f32 = 48000.0;
+
+    fn float(value: f32, min: f32, max: f32) -> FloatParam {
+        FloatParam::new("Test", value, FloatRange::Linear { min, max })
+    }
+
+    fn master() -> MasterParams {
+        let master = MasterParams::default();
+        settle_smoothers(&master);
+        master
+    }
+
+    /// A slot's params with the given edits, smoothers settled.
+    fn slot_params(
+        defaults: fn() -> DrumSlotParams,
+        edit: impl FnOnce(&mut DrumSlotParams),
+    ) -> DrumSlotParams {
+        let mut params = defaults();
+        params.humanize = float(0.0, 0.0, 1.0);
+        edit(&mut params);
+        settle_smoothers(&params);
+        params
+    }
+
+    /// Trigger `slot` and render `len` samples of its left channel.
+    fn hit(
+        slot: &mut DrumSlot,
+        params: &DrumSlotParams,
+        master: &MasterParams,
+        velocity: f32,
+        len: usize,
+    ) -> Vec<f32> {
+        slot.trigger(velocity, 0.0, params, master);
+        (0..len)
+            .map(|_| slot.process(params, master, None).0)
+            .collect()
+    }
+
+    fn peak(x: &[f32]) -> f32 {
+        x.iter().fold(0.0, |m, v| m.max(v.abs()))
+    }
+
+    fn rms(x: &[f32]) -> f32 {
+        (x.iter().map(|v| v * v).sum::<f32>() / x.len().max(1) as f32).sqrt()
+    }
+
+    #[test]
+    fn shortest_decay_stops_at_the_engine_floor() {
+        let master = master();
+        for (kind, mut params) in [
+            (SlotType::Kick, DrumSlotParams::default_kick()),
+            (SlotType::HatClosed, DrumSlotParams::default_hat_closed()),
+        ] {
+            params.decay = float(10.0, 10.0, 2000.0);
+            params.humanize = float(1.0, 0.0, 1.0);
+            params.decay_link = float(1.0, 0.0, 1.0);
+            params.pitch = float(24.0, -24.0, 24.0);
+            settle_smoothers(&params);
+
+            let floor = kind.min_decay_ms();
+            let mut slot = DrumSlot::new(kind, SR);
+            // Humanize and decay link both pull the decay down further; neither gets past the floor
+            for _ in 0..32 {
+                slot.trigger(1.0, 0.0, &params, &master);
+                assert!(
+                    slot.decay_ms() >= floor - 0.01,
+                    "{kind:?} decayed in {} ms",
+                    slot.decay_ms()
+                );
+            }
+        }
+
+        // Without the extra pulls the kick sits right on its floor, the hat on the param
+        let mut kick = DrumSlotParams::default_kick();
+        kick.decay = float(10.0, 10.0, 2000.0);
+        kick.humanize = float(0.0, 0.0, 1.0);
+        settle_smoothers(&kick);
+        let mut slot = DrumSlot::new(SlotType::Kick, SR);
+        slot.trigger(1.0, 0.0, &kick, &master);
+        assert!((slot.decay_ms() - SlotType::Kick.min_decay_ms()).abs() < 0.1);
+        assert!(SlotType::Kick.min_decay_ms() > SlotType::HatClosed.min_decay_ms());
+    }
+
+    #[test]
+    fn reverse_swells_to_its_peak_at_the_end() {
+        let master = master();
+        let render = |reverse: bool| {
+            let params = slot_params(DrumSlotParams::default_tom, |p| {
+                p.decay = float(200.0, 10.0, 2000.0);
+                p.reverse = BoolParam::new("Reverse", reverse);
+            });
+            hit(
+                &mut DrumSlot::new(SlotType::Tom, SR),
+                &params,
+                &master,
+                1.0,
+                48000,
+            )
+        };
+        // The swell spans three time constants, 600 ms
+        let window = (3.0 * 0.2 * SR) as usize;
+        let when = |x: &[f32]| {
+            let peaks: Vec<f32> = x.chunks(480).map(peak).collect();
+            (0..peaks.len())
+                .max_by(|&a, &b| peaks[a].total_cmp(&peaks[b]))
+                .unwrap()
+                * 480
+        };
+
+        let forward = render(false);
+        assert!(when(&forward) < window / 10);
+
+        let reversed = render(true);
+        let at = when(&reversed);
+        assert!(
+            at > window * 8 / 10 && at <= window,
+            "reverse peaked at {at} of {window}"
+        );
+        // Then it cuts instead of ringing on
+        assert!(peak(&reversed[window + 480..]) < 1e-3);
+    }
+
+    #[test]
+    fn start_offset_lowers_the_hit_but_keeps_the_decay() {
+        let master = master();
+        let render = |start: f32| {
+            let params = slot_params(DrumSlotParams::default_tom, |p| {
+                p.start = float(start, 0.0, 1.0);
+                p.clean = BoolParam::new("Clean", true);
+            });
+            hit(
+                &mut DrumSlot::new(SlotType::Tom, SR),
+                &params,
+                &master,
+                1.0,
+                9600,
+            )
+        };
+        let (full, late) = (render(1.0), render(0.3));
+        assert!(peak(&late) < 0.35 * peak(&full));
+        // Same curve, just further along it: every 20 ms window sits at the same ratio
+        for (a, b) in full.chunks(960).zip(late.chunks(960)) {
+            assert!((peak(b) / peak(a) - 0.3).abs() < 0.003);
+        }
+        // Even at zero the hit starts above the voice's cutoff
+        assert!(peak(&render(0.0)) > 0.0);
+    }
+
+    #[test]
+    fn gentle_noise_keeps_hats_out_of_the_ultrasonics() {
+        let sr = 96000.0;
+        let params = slot_params(DrumSlotParams::default_hat_closed, |p| {
+            p.decay = float(400.0, 10.0, 2000.0);
+        });
+        let ultrasonic = |gentle: bool| {
+            let master = MasterParams {
+                gentle_noise: BoolParam::new("Gentle Noise", gentle),
+                ..MasterParams::default()
+            };
+            settle_smoothers(&master);
+            let out = hit(
+                &mut DrumSlot::new(SlotType::HatClosed, sr),
+                &params,
+                &master,
+                1.0,
+                9600,
+            );
+            let spectrum = analysis::spectrum(&out, sr, 256);
+            analysis::band_energy(&spectrum, sr, 18000.0, sr * 0.5)
+        };
+        let (full, gentle) = (ultrasonic(false), ultrasonic(true));
+        assert!(
+            gentle < 0.25 * full,
+            "energy above 18 kHz: {gentle} gentle vs {full} full band"
+        );
+    }
+
+    #[test]
+    fn glide_slides_between_hits_instead_of_stepping() {
+        let master = master();
+        let params = slot_params(DrumSlotParams::default_tom, |p| {
+            p.glide = float(50.0, 0.0, 500.0);
+        });
+        let mut slot = DrumSlot::new(SlotType::Tom, SR);
+        hit(&mut slot, &params, &master, 1.0, 4800);
+        let from = slot.base_freq;
+
+        // An octave up, gliding over 50 ms
+        slot.trigger(1.0, 12.0, &params, &master);
+        let freqs: Vec<f32> = (0..4800)
+            .map(|_| {
+                slot.process(&params, &master, None);
+                slot.base_freq
+            })
+            .collect();
+        let glide = (0.05 * SR) as usize;
+        assert!((freqs[glide / 2] / from - 2.0f32.sqrt()).abs() < 0.01);
+        assert!((freqs[glide + 10] / from - 2.0).abs() < 1e-3);
+        // Rising all the way, in small steps
+        let step = 2.0f32.powf(1.0 / glide as f32) * 1.0001;
+        assert!(
+            freqs[..glide]
+                .windows(2)
+                .all(|w| w[1] > w[0] && w[1] / w[0] < step)
+        );
+
+        // Without glide the new pitch lands at once
+        let instant = slot_params(DrumSlotParams::default_tom, |_| {});
+        let mut slot = DrumSlot::new(SlotType::Tom, SR);
+        hit(&mut slot, &instant, &master, 1.0, 480);
+        slot.trigger(1.0, 12.0, &instant, &master);
+        assert!((slot.base_freq / from - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn eq_high_boost_brightens_the_kick() {
+        let master = master();
+        let bands = |high_db: f32| {
+            let params = slot_params(DrumSlotParams::default_kick, |p| {
+                p.eq_high = float(high_db, -12.0, 12.0);
+            });
+            let out = hit(
+                &mut DrumSlot::new(SlotType::Kick, SR),
+                &params,
+                &master,
+                1.0,
+                9600,
+            );
+            let spectrum = analysis::spectrum(&out, SR, 256);
+            (
+                analysis::band_energy(&spectrum, SR, 0.0, 150.0),
+                analysis::band_energy(&spectrum, SR, 8000.0, SR * 0.5),
+            )
+        };
+        let ((flat_low, flat_high), (low, high)) = (bands(0.0), bands(12.0));
+        // +12 dB is about 16× the power on the shelf
+        assert!(
+            high > 8.0 * flat_high,
+            "high band {high} boosted vs {flat_high} flat"
+        );
+        assert!((low / flat_low - 1.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn triangle_body_adds_odd_harmonics_to_the_tom() {
+        let master = master();
+        // Third and fifth harmonics relative to the fundamental
+        let odd_ratio = |wave: i32| {
+            let params = slot_params(DrumSlotParams::default_tom, |p| {
+                p.body_wave = IntParam::new("Test", wave, IntRange::Linear { min: 0, max: 2 });
+            });
+            let mut slot = DrumSlot::new(SlotType::Tom, SR);
+            let out = hit(&mut slot, &params, &master, 1.0, 4800);
+            let f0 = slot.base_freq;
+            let odd: f32 = [3.0, 5.0]
+                .iter()
+                .map(|h| analysis::goertzel(&out, SR, f0 * h))
+                .sum();
+            odd / analysis::goertzel(&out, SR, f0)
+        };
+        let (sine, triangle) = (odd_ratio(0), odd_ratio(1));
+        // An ideal triangle has 1/9 + 1/25 of its fundamental there; the sine only
+        // the tom's noise
+        assert!(
+            triangle > 2.0 * sine,
+            "odd harmonics {triangle} triangle vs {sine} sine"
+        );
+    }
+
+    #[test]
+    fn velocity_crossfades_between_soft_and_hard_tone() {
+        let master = master();
+        let layered = slot_params(DrumSlotParams::default_snare, |p| {
+            p.tone_soft = float(-0.4, -1.0, 1.0);
+            p.tone_hard = float(0.4, -1.0, 1.0);
+        });
+        let plain = slot_params(DrumSlotParams::default_snare, |_| {});
+        let tone_at = |params: &DrumSlotParams, velocity: f32| {
+            let mut slot = DrumSlot::new(SlotType::Snare, SR);
+            slot.trigger(velocity, 0.0, params, &master);
+            slot.process(params, &master, None);
+            slot.shaped_tone(params)
+        };
+
+        // Both targets at their default leave the tone alone at any velocity
+        let tone = tone_at(&plain, 1.0);
+        assert!((tone_at(&plain, 0.05) - tone).abs() < 1e-6);
+
+        let soft = tone_at(&layered, 0.0);
+        let mid = tone_at(&layered, 0.5);
+        let hard = tone_at(&layered, 1.0);
+        assert!((soft - shape_macro(tone - 0.4, 0.0)).abs() < 1e-4);
+        assert!((hard - shape_macro(tone + 0.4, 0.0)).abs() < 1e-4);
+        assert!(soft < mid && mid < hard);
+    }
+
+    #[test]
+    fn tone_automation_glides_the_cutoff() {
+        let master = master();
+        let params = slot_params(DrumSlotParams::default_tom, |p| {
+            // As declared, with its smoother
+            p.tone = float(0.1, 0.0, 1.0).with_smoother(SmoothingStyle::Linear(20.0));
+        });
+        let mut slot = DrumSlot::new(SlotType::Tom, SR);
+        hit(&mut slot, &params, &master, 1.0, 64);
+
+        // The host jumps the tone mid-hit; the tom's noise cutoff follows it
+        params.tone.smoothed.set_target(SR, 0.9);
+        let cutoffs: Vec<f32> = (0..2400)
+            .map(|_| {
+                slot.process(&params, &master, None);
+                1500.0 + 3000.0 * slot.shaped_tone(&params)
+            })
+            .collect();
+        let full = 3000.0 * 0.8;
+        let ramp = (0.02 * SR) as usize;
+        assert!((cutoffs[ramp / 2] - cutoffs[0] - full / 2.0).abs() < full * 0.05);
+        assert!((cutoffs[ramp + 10] - 1500.0 - 3000.0 * 0.9).abs() < 1.0);
+        // No sample-to-sample step bigger than an even 20 ms ramp takes
+        let step = full / ramp as f32 * 1.01;
+        assert!(cutoffs.windows(2).all(|w| (w[1] - w[0]).abs() <= step));
+    }
+
+    #[test]
+    fn low_pass_noise_is_darker_than_high_pass() {
+        let master = master();
+        let low_energy = |mode: i32| {
+            let params = slot_params(DrumSlotParams::default_hat_open, |p| {
+                p.noise_filter = IntParam::new("Test", mode, IntRange::Linear { min: 0, max: 2 });
+            });
+            let out = hit(
+                &mut DrumSlot::new(SlotType::HatOpen, SR),
+                &params,
+                &master,
+                1.0,
+                9600,
+            );
+            let spectrum = analysis::spectrum(&out, SR, 256);
+            analysis::band_energy(&spectrum, SR, 0.0, 2000.0)
+        };
+        let (hp_low, lp_low) = (low_energy(0), low_energy(2));
+        assert!(
+            lp_low > 4.0 * hp_low,
+            "below 2 kHz: {lp_low} low-pass vs {hp_low} high-pass"
+        );
+    }
+
+    #[test]
+    fn sub_adds_energy_an_octave_below_the_kick() {
+        let master = master();
+        let at_half = |sub: f32| {
+            let params = slot_params(DrumSlotParams::default_kick, |p| {
+                p.sub = float(sub, 0.0, 1.0);
+            });
+            let mut slot = DrumSlot::new(SlotType::Kick, SR);
+            let out = hit(&mut slot, &params, &master, 1.0, 19200);
+            // Past the pitch sweep, where the body sits at its base pitch
+            let tail = &out[4800..];
+            analysis::goertzel(tail, SR, slot.base_freq * 0.5)
+        };
+        let (plain, sub) = (at_half(0.0), at_half(1.0));
+        assert!(
+            sub > 4.0 * plain,
+            "at f0/2: {sub} with Sub vs {plain} without"
+        );
+    }
+
+    #[test]
+    fn smooth_rolls_fill_the_dip_after_a_retrigger() {
+        let master = master();
+        // A clap roll, one hit every 25 ms, its noise low-passed at 800 Hz: the noise level just after each retrigger
+        // against just before it
+        let dip = |smooth: bool| {
+            let params = slot_params(DrumSlotParams::default_clap, |p| {
+                p.noise_filter = IntParam::new("Test", 2, IntRange::Linear { min: 0, max: 2 });
+                p.tone = float(1.0, 0.0, 1.0);
+                p.smooth_rolls = BoolParam::new("Test", smooth);
+            });
+            let mut slot = DrumSlot::new(SlotType::Clap, SR);
+            let spacing = (0.025 * SR) as usize;
+            let window = 8;
+            let roll: Vec<Vec<f32>> = (0..16)
+                .map(|_| hit(&mut slot, &params, &master, 0.8, spacing))
+                .collect();
+            let (before, after): (f32, f32) = roll
+                .windows(2)
+                .map(|w| (rms(&w[0][spacing - window..]), rms(&w[1][..window])))
+                .fold((0.0, 0.0), |(b, a), (x, y)| (b + x, a + y));
+            after / before
+        };
+        let (reset, smooth) = (dip(false), dip(true));
+        assert!(
+            smooth > 1.1 * reset,
+            "level after a retrigger {smooth} smoothed vs {reset} reset"
+        );
+    }
+
+    #[test]
+    fn loudest_wins_ignores_a_ghost_retrigger() {
+        let master = master();
+        // An accent, then a ghost note 20 ms in; or the accent alone
+        let render = |loudest_wins: bool, ghost: bool| {
+            let params = slot_params(DrumSlotParams::default_snare, |p| {
+                p.loudest_wins = BoolParam::new("Test", loudest_wins);
+            });
+            let mut slot = DrumSlot::new(SlotType::Snare, SR);
+            let mut out = hit(&mut slot, &params, &master, 1.0, 960);
+            if ghost {
+                out.extend(hit(&mut slot, &params, &master, 0.2, 4800));
+            } else {
+                out.extend((0..4800).map(|_| slot.process(&params, &master, None).0));
+            }
+            out
+        };
+        assert_eq!(render(true, true), render(true, false));
+
+        // Always-newest: the ghost cuts the accent down
+        let (cut, accent) = (render(false, true), render(false, false));
+        assert!(peak(&cut[960..]) < 0.5 * peak(&accent[960..]));
+
+        // A harder hit still gets through
+        let params = slot_params(DrumSlotParams::default_snare, |p| {
+            p.loudest_wins = BoolParam::new("Test", true);
+        });
+        let mut slot = DrumSlot::new(SlotType::Snare, SR);
+        hit(&mut slot, &params, &master, 0.3, 960);
+        let soft_env = slot.env;
+        hit(&mut slot, &params, &master, 1.0, 1);
+        assert!(slot.env > soft_env);
+    }
+
+    #[test]
+    fn env_filter_sweeps_the_noise_cutoff_over_the_hit() {
+        let master = master();
+        // Share of the noise above 2 kHz on the attack and late in the decay
+        let brightness = |env_filter: f32| {
+            let params = slot_params(DrumSlotParams::default_clap, |p| {
+                p.noise_filter = IntParam::new("Test", 2, IntRange::Linear { min: 0, max: 2 });
+                // Low-passed at 800 Hz when unmodulated
+                p.tone = float(1.0, 0.0, 1.0);
+                p.decay = float(150.0, 10.0, 2000.0);
+                p.env_filter = float(env_filter, -1.0, 1.0);
+            });
+            let out = hit(
+                &mut DrumSlot::new(SlotType::Clap, SR),
+                &params,
+                &master,
+                1.0,
+                19200,
+            );
+            let share = |x: &[f32]| {
+                let spectrum = analysis::spectrum(x, SR, 128);
+                analysis::band_energy(&spectrum, SR, 2000.0, SR * 0.5)
+                    / analysis::band_energy(&spectrum, SR, 0.0, SR * 0.5)
+            };
+            (share(&out[..960]), share(&out[9600..]))
+        };
+        // How much brighter the attack is than the tail, against the unmodulated hit
+        let sweep = |env_filter: f32| {
+            let ((attack, late), (flat_attack, flat_late)) =
+                (brightness(env_filter), brightness(0.0));
+            (attack / late) / (flat_attack / flat_late)
+        };
+        // Closing on the attack: dark at first, opening up as the envelope falls
+        assert!(
+            sweep(-1.0) < 0.5,
+            "attack brightness {} of flat",
+            sweep(-1.0)
+        );
+        // Opening on the attack: the reverse
+        assert!(sweep(1.0) > 2.0, "attack brightness {} of flat", sweep(1.0));
+    }
+
+    #[test]
+    fn tone_compensation_holds_hit_loudness_across_a_sweep() {
+        let master = master();
+        // Per-hit level in dB as tone sweeps across its range, one hit per step
+        let sweep = |compensate: bool| {
+            let mut slot = DrumSlot::new(SlotType::Snare, SR);
+            (0..=8)
+                .map(|step| {
+                    let params = slot_params(DrumSlotParams::default_snare, |p| {
+                        p.tone = float(step as f32 / 8.0, 0.0, 1.0);
+                        p.tone_comp = BoolParam::new("Test", compensate);
+                    });
+                    20.0 * rms(&hit(&mut slot, &params, &master, 0.8, 9600)).log10()
+                })
+                .collect::<Vec<f32>>()
+        };
+        let spread = |db: &[f32]| {
+            db.iter().fold(f32::MIN, |m, &x| m.max(x)) - db.iter().fold(f32::MAX, |m, &x| m.min(x))
+        };
+        assert!(spread(&sweep(false)) > 2.0);
+        assert!(spread(&sweep(true)) < 1.0, "levels {:?}", sweep(true));
+    }
+
+    #[test]
+    fn sample_layer_plays_under_the_hit() {
+        let master = master();
+        let params = slot_params(DrumSlotParams::default_kick, |p| {
+            p.clean = BoolParam::new("Test", true);
+        });
+        // A click 100 samples into a 24 kHz one-shot: 200 samples in at 48 kHz
+        let mut pcm = vec![0.0; 1000];
+        pcm[100] = 1.0;
+        let render = |layer: Option<(f32, &[f32])>| {
+            let mut slot = DrumSlot::new(SlotType::Kick, SR);
+            slot.trigger(0.5, 0.0, &params, &master);
+            (0..4800)
+                .map(|_| slot.process(&params, &master, layer).0)
+                .collect::<Vec<f32>>()
+        };
+        let layered: Vec<f32> = render(Some((24000.0, &pcm)))
+            .iter()
+            .zip(render(None))
+            .map(|(with, without)| with - without)
+            .collect();
+
+        // The click, at the hit's velocity, and nothing else once the sample has ended
+        let at = layered
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+            .unwrap()
+            .0;
+        assert_eq!(at, 200);
+        assert!(layered[200] > 0.3, "click {}", layered[200]);
+        assert!(peak(&layered[2000..]) < 1e-6);
+    }
+
+    #[test]
+    fn clean_slot_scales_linearly_with_velocity() {
+        let master = master();
+        // The same kick at two velocities: sample by sample, how far the softer hit
+        // strays from a scaled copy of the harder one
+        let bend = |clean: bool| {
+            let params = slot_params(DrumSlotParams::default_kick, |p| {
+                p.clean = BoolParam::new("Test", clean);
+            });
+            let render = |velocity| {
+                hit(
+                    &mut DrumSlot::new(SlotType::Kick, SR),
+                    &params,
+                    &master,
+                    velocity,
+                    4800,
+                )
+            };
+            let (hard, soft) = (render(1.0), render(0.5));
+            let scale = peak(&soft) / peak(&hard);
+            hard.iter()
+                .zip(&soft)
+                .map(|(h, s)| (h * scale - s).abs())
+                .fold(0.0, f32::max)
+                / peak(&soft)
+        };
+        let (saturated, clean) = (bend(false), bend(true));
+        assert!(saturated > 0.05);
+        assert!(clean < 1e-4, "clean hit strays {clean} from linear");
+    }
+
+    #[test]
+    fn vel_drive_dirties_hard_hits_and_spares_soft_ones() {
+        let master = master();
+        // Third harmonic against the fundamental of a tom, its body settled at pitch
+        let grit = |vel_drive: f32, velocity: f32| {
+            let params = slot_params(DrumSlotParams::default_tom, |p| {
+                p.vel_drive = float(vel_drive, 0.0, 1.0);
+            });
+            let mut slot = DrumSlot::new(SlotType::Tom, SR);
+            let out = hit(&mut slot, &params, &master, velocity, 4800);
+            let f0 = slot.base_freq;
+            analysis::goertzel(&out[480..], SR, 3.0 * f0) / analysis::goertzel(&out[480..], SR, f0)
+        };
+        let (hard, hard_plain) = (grit(1.0, 1.0), grit(0.0, 1.0));
+        let (soft, soft_plain) = (grit(1.0, 0.3), grit(0.0, 0.3));
+        assert!(
+            hard > 2.0 * hard_plain,
+            "hard hit: {hard} driven vs {hard_plain}"
+        );
+        assert!(
+            soft < 1.5 * soft_plain,
+            "soft hit: {soft} driven vs {soft_plain}"
+        );
+        assert!(hard > 2.0 * soft);
+    }
+
+    #[test]
+    fn keytracked_hats_darken_with_the_kit_pitch() {
+        // Share of a closed hat's energy above 6 kHz, its noise low-passed at 6 kHz
+        let brightness = |key_track: bool, kit_pitch: f32| {
+            let master = MasterParams {
+                kit_pitch: float(kit_pitch, -12.0, 12.0),
+                ..MasterParams::default()
+            };
+            settle_smoothers(&master);
+            let params = slot_params(DrumSlotParams::default_hat_closed, |p| {
+                p.key_track = BoolParam::new("Test", key_track);
+                // Low-passed, so the cutoff shows plainly in the spectrum
+                p.tone = float(0.0, 0.0, 1.0);
+                p.noise_filter = IntParam::new("Test", 2, IntRange::Linear { min: 0, max: 2 });
+            });
+            let out = hit(
+                &mut DrumSlot::new(SlotType::HatClosed, SR),
+                &params,
+                &master,
+                1.0,
+                4800,
+            );
+            let spectrum = analysis::spectrum(&out, SR, 256);
+            analysis::band_energy(&spectrum, SR, 6000.0, SR * 0.5)
+                / analysis::band_energy(&spectrum, SR, 0.0, SR * 0.5)
+        };
+        // Without keytracking the noise ignores the transpose
+        assert!((brightness(false, -12.0) / brightness(false, 0.0) - 1.0).abs() < 0.05);
+        // With it, an octave down halves the cutoff
+        let (down, level) = (brightness(true, -12.0), brightness(true, 0.0));
+        assert!(
+            down < 0.8 * level,
+            "above 6 kHz: {down} an octave down vs {level}"
+        );
+    }
+
+    #[test]
+    fn relative_tails_let_loud_hits_ring_longer() {
+        // Samples until a hit's voice shuts off
+        let ring = |relative: bool, velocity: f32| {
+            let master = MasterParams {
+                relative_tails: BoolParam::new("Test", relative),
+                ..MasterParams::default()
+            };
+            settle_smoothers(&master);
+            let params = slot_params(DrumSlotParams::default_snare, |_| {});
+            let mut slot = DrumSlot::new(SlotType::Snare, SR);
+            slot.trigger(velocity, 0.0, &params, &master);
+            (0..10 * SR as usize)
+                .position(|_| {
+                    slot.process(&params, &master, None);
+                    !slot.is_active()
+                })
+                .unwrap()
+        };
+        // Fixed: every hit stops at the same envelope level
+        assert_eq!(ring(false, 1.0), ring(false, 0.2));
+        // Level-relative: the loud hit outlasts the quiet one
+        let (loud, quiet) = (ring(true, 1.0), ring(true, 0.2));
+        assert!(loud > quiet, "loud {loud} vs quiet {quiet} samples");
+    }
+
+    #[test]
+    fn ring_brings_the_perc_pitch_forward() {
+        let master = master();
+        // Share of the hit's energy right around the ringing partial
+        let tonal_share = |ring: f32| {
+            let params = slot_params(DrumSlotParams::default_perc1, |p| {
+                p.ring = float(ring, 0.0, 1.0);
+            });
+            let mut slot = DrumSlot::new(SlotType::Perc1, SR);
+            let out = hit(&mut slot, &params, &master, 1.0, 9600);
+            let pitch = slot.base_freq * (1.5 + 0.5 * slot.shaped_tone(&params));
+            let spectrum = analysis::spectrum(&out, SR, 256);
+            analysis::band_energy(&spectrum, SR, pitch * 0.9, pitch * 1.1)
+                / analysis::band_energy(&spectrum, SR, 0.0, SR * 0.5)
+        };
+        let (noisy, default, ringy) = (tonal_share(0.1), tonal_share(0.25), tonal_share(0.9));
+        assert!(
+            noisy < default && default < ringy,
+            "{noisy} < {default} < {ringy}"
+        );
+        assert!(ringy > 4.0 * noisy, "{ringy} vs {noisy}");
+    }
+
+    #[test]
+    fn scale_snaps_a_chromatic_run_onto_its_degrees() {
+        let master = master();
+        // Semitones above the untransposed tom each note of the run lands on
+        let run = |scale: i32| {
+            let params = slot_params(DrumSlotParams::default_tom, |p| {
+                p.scale = IntParam::new("Test", scale, IntRange::Linear { min: 0, max: 3 });
+            });
+            let root = {
+                let mut slot = DrumSlot::new(SlotType::Tom, SR);
+                slot.trigger(1.0, 0.0, &params, &master);
+                slot.base_freq
+            };
+            (0..=24)
+                .map(|note| {
+                    let mut slot = DrumSlot::new(SlotType::Tom, SR);
+                    slot.trigger(1.0, note as f32, &params, &master);
+                    12.0 * (slot.base_freq / root).log2()
+                })
+                .collect::<Vec<f32>>()
+        };
+        // Chromatic plays every note as asked
+        for (note, semis) in run(0).into_iter().enumerate() {
+            assert!((semis - note as f32).abs() < 1e-3, "{note}: {semis}");
+        }
+        for (scale, name) in SCALES.iter().enumerate().skip(1) {
+            let degrees = SCALE_DEGREES[scale];
+            let run = run(scale as i32);
+            for (note, &semis) in run.iter().enumerate() {
+                let within = semis.rem_euclid(12.0);
+                assert!(
+                    degrees.iter().any(|d| (within - d).abs() < 1e-3)
+                        || (12.0 - within).abs() < 1e-3,
+                    "{name}: note {note} at {semis}"
+                );
+                assert!((semis - note as f32).abs() <= 1.0 + 1e-3, "{name}: {note}");
+            }
+            // Every degree gets played, and the run never steps down
+            assert!(run.windows(2).all(|w| w[1] >= w[0] - 1e-3));
+            for d in degrees {
+                assert!(run.iter().any(|s| (s - d).abs() < 1e-3), "{name}: {d}");
+            }
+        }
+    }
+
+    #[test]
+    fn velocity_floor_lifts_soft_taps_and_ceiling_caps_accents() {
+        // A straight velocity curve, so the remap is what the voice sees
+        let master = MasterParams {
+            velocity_curve: float(0.5, 0.0, 1.0),
+            ..MasterParams::default()
+        };
+        settle_smoothers(&master);
+        let velocity = |floor: f32, ceiling: f32, incoming: f32| {
+            let params = slot_params(DrumSlotParams::default_snare, |p| {
+                p.vel_floor = float(floor, 0.0, 1.0);
+                p.vel_ceiling = float(ceiling, 0.0, 1.0);
+            });
+            let mut slot = DrumSlot::new(SlotType::Snare, SR);
+            let out = hit(&mut slot, &params, &master, incoming, 2400);
+            (slot.velocity, peak(&out))
+        };
+        // Identity by default
+        assert!((velocity(0.0, 1.0, 0.1).0 - 0.1).abs() < 1e-6);
+        assert!((velocity(0.0, 1.0, 1.0).0 - 1.0).abs() < 1e-6);
+
+        // A 0.1 tap with a 0.3 floor plays at least at the floor, and louder
+        let (soft, soft_peak) = velocity(0.3, 1.0, 0.1);
+        assert!(soft >= 0.3, "{soft}");
+        assert!(soft_peak > velocity(0.0, 1.0, 0.1).1 * 2.0);
+        // A full accent stops at the ceiling
+        let (hard, hard_peak) = velocity(0.0, 0.8, 1.0);
+        assert!((hard - 0.8).abs() < 1e-6, "{hard}");
+        assert!(hard_peak < velocity(0.0, 1.0, 1.0).1);
+    }
+
+    #[test]
+    fn attack_noise_adds_broadband_grit_only_at_the_start() {
+        let master = master();
+        let render = |amount: f32| {
+            let params = slot_params(DrumSlotParams::default_kick, |p| {
+                p.attack_noise = float(amount, 0.0, 1.0);
+            });
+            hit(
+                &mut DrumSlot::new(SlotType::Kick, SR),
+                &params,
+                &master,
+                1.0,
+                4800,
+            )
+        };
+        let (clean, gritty) = (render(0.0), render(1.0));
+        let added: Vec<f32> = gritty.iter().zip(&clean).map(|(g, c)| g - c).collect();
+
+        // Full of highs well above the kick's own content...
+        let first = (0.005 * SR) as usize;
+        let spectrum = analysis::spectrum(&added[..first], SR, 64);
+        let clean_spectrum = analysis::spectrum(&clean[..first], SR, 64);
+        let high = |s: &[f32]| analysis::band_energy(s, SR, 6000.0, 20000.0);
+        assert!(high(&spectrum) > 4.0 * high(&clean_spectrum));
+        // ...and gone after the first few milliseconds
+        let late = (0.02 * SR) as usize;
+        assert!(
+            rms(&added[late..]) < 1e-3 * rms(&added[..first]),
+            "{} late vs {} early",
+            rms(&added[late..]),
+            rms(&added[..first])
+        );
+    }
+
+    #[test]
+    fn injected_rng_sets_the_humanize_draws_exactly() {
+        // Steps through a fixed sequence, the state counting draws
+        fn sequence(state: &mut u32) -> f32 {
+            const DRAWS: [f32; 4] = [0.5, -0.8, 0.25, -1.0];
+            let draw = DRAWS[*state as usize % DRAWS.len()];
+            *state = state.wrapping_add(1);
+            draw
+        }
+        let master = master();
+        let params = slot_params(DrumSlotParams::default_tom, |p| {
+            p.humanize = float(0.5, 0.0, 1.0);
+        });
+        let mut slot = DrumSlot::new(SlotType::Tom, SR);
+        slot.set_random_source(sequence);
+        // Seeded so the trigger's reseed step (one LCG step) brings the count to 0
+        slot.seed(0u32.wrapping_sub(1013904223).wrapping_mul(4276115653));
+        slot.trigger(1.0, 0.0, &params, &master);
+
+        // Amp, pitch and decay take the first three draws, in that order
+        assert!((slot.human_amp - (1.0 + 0.5 * 0.15 * 0.5)).abs() < 1e-6);
+        assert!((slot.human_pitch - -0.8 * 3.0 * 0.5).abs() < 1e-6);
+        assert!((slot.human_decay_mul - (1.0 + 0.25 * 0.5 * 0.5)).abs() < 1e-6);
+
+        // The default source gives something else entirely
+        let mut slot = DrumSlot::new(SlotType::Tom, SR);
+        slot.trigger(1.0, 0.0, &params, &master);
+        assert!((slot.human_pitch - -1.2).abs() > 1e-3);
+    }
+
+    #[test]
+    fn tuning_reference_scales_every_slot_pitch() {
+        let base_freq = |kind: SlotType, params: &DrumSlotParams, a4: f32| {
+            let master = MasterParams {
+                tuning: float(a4, 415.0, 466.0),
+                ..MasterParams::default()
+            };
+            settle_smoothers(&master);
+            let mut slot = DrumSlot::new(kind, SR);
+            slot.trigger(1.0, 0.0, params, &master);
+            slot.base_freq
+        };
+        for (kind, defaults) in [
+            (SlotType::Kick, DrumSlotParams::default_kick as fn() -> _),
+            (SlotType::Snare, DrumSlotParams::default_snare),
+            (SlotType::Clap, DrumSlotParams::default_clap),
+            (SlotType::HatClosed, DrumSlotParams::default_hat_closed),
+            (SlotType::HatOpen, DrumSlotParams::default_hat_open),
+            (SlotType::Tom, DrumSlotParams::default_tom),
+            (SlotType::Perc1, DrumSlotParams::default_perc1),
+            (SlotType::Perc2, DrumSlotParams::default_perc2),
+        ] {
+            let params = slot_params(defaults, |_| {});
+            let ratio = base_freq(kind, &params, 432.0) / base_freq(kind, &params, 440.0);
+            assert!((ratio - 432.0 / 440.0).abs() < 1e-5, "{ratio}");
+        }
+    }
+
+    #[test]
+    fn decay_link_shortens_a_tom_tuned_up() {
+        let master = master();
+        // The hit's decay time constant
+        let ring = |link: f32, pitch: f32| {
+            let params = slot_params(DrumSlotParams::default_tom, |p| {
+                p.decay_link = float(link, 0.0, 1.0);
+                p.pitch = float(pitch, -24.0, 24.0);
+            });
+            let mut slot = DrumSlot::new(SlotType::Tom, SR);
+            slot.trigger(1.0, 0.0, &params, &master);
+            slot.decay_ms()
+        };
+        // Without the link an octave up rings as long
+        let (low, high) = (ring(0.0, 0.0), ring(0.0, 12.0));
+        assert!((high / low - 1.0).abs() < 1e-3, "{low} vs {high}");
+        // With it fully on, the octave halves the decay
+        let (low, high) = (ring(1.0, 0.0), ring(1.0, 12.0));
+        assert!((high / low - 0.5).abs() < 1e-3, "{low} vs {high}");
+    }
+
+    #[test]
+    fn gaussian_humanize_clusters_around_the_centre() {
+        let params = slot_params(DrumSlotParams::default_snare, |p| {
+            p.humanize = float(1.0, 0.0, 1.0);
+        });
+        // Pitch humanize (as a fraction of its ±3 semitones) over 10000 hits, and
+        // how many land within a quarter of the centre
+        let draws = |dist: i32| {
+            let master = MasterParams {
+                humanize_dist: IntParam::new("Test", dist, IntRange::Linear { min: 0, max: 1 }),
+                ..MasterParams::default()
+            };
+            settle_smoothers(&master);
+            let mut slot = DrumSlot::new(SlotType::Snare, SR);
+            let draws: Vec<f32> = (0..10000)
+                .map(|_| {
+                    slot.trigger(1.0, 0.0, &params, &master);
+                    slot.human_pitch / 3.0
+                })
+                .collect();
+            let near = draws.iter().filter(|x| x.abs() < 0.25).count() as f32 / 10000.0;
+            (draws, near)
+        };
+        let (uniform, uniform_near) = draws(0);
+        let (gaussian, gaussian_near) = draws(1);
+        // Same range, both centred
+        for d in [&uniform, &gaussian] {
+            assert!(d.iter().all(|x| (-1.0..=1.0).contains(x)));
+            assert!((d.iter().sum::<f32>() / d.len() as f32).abs() < 0.02);
+        }
+        // Flat: a quarter of the draws in the middle quarter; clustered: over half
+        assert!((uniform_near - 0.25).abs() < 0.02, "{uniform_near}");
+        assert!(gaussian_near > 0.5, "{gaussian_near}");
+        // A third of the variance
+        let ratio = rms(&gaussian).powi(2) / rms(&uniform).powi(2);
+        assert!((ratio - 1.0 / 3.0).abs() < 0.03, "{ratio}");
+    }
+
+    #[test]
+    fn phase_random_starts_each_hit_somewhere_new() {
+        let master = master();
+        let starts = |amount: f32| {
+            let params = slot_params(DrumSlotParams::default_tom, |p| {
+                p.phase_random = float(amount, 0.0, 1.0);
+            });
+            let mut slot = DrumSlot::new(SlotType::Tom, SR);
+            (0..8)
+                .map(|_| {
+                    slot.trigger(1.0, 0.0, &params, &master);
+                    slot.osc_phase
+                })
+                .collect::<Vec<f32>>()
+        };
+        // Off: every hit starts from zero
+        assert!(starts(0.0).iter().all(|&phase| phase == 0.0));
+
+        // On: no two successive hits share a phase, and all stay within the amount
+        let half = starts(0.5);
+        assert!(
+            half.windows(2).all(|w| (w[1] - w[0]).abs() > 1e-3),
+            "{half:?}"
+        );
+        assert!(half.iter().all(|phase| (0.0..=PI).contains(phase)));
+    }
+
+    #[test]
+    fn wide_tone_moves_brightness_and_body_balance_together() {
+        let master = master();
+        // Share of the snare's energy up top, and around its body's pitch
+        let shares = |wide: bool, tone: f32| {
+            let params = slot_params(DrumSlotParams::default_snare, |p| {
+                p.wide_tone = BoolParam::new("Test", wide);
+                p.tone = float(tone, 0.0, 1.0);
+            });
+            let mut slot = DrumSlot::new(SlotType::Snare, SR);
+            let out = hit(&mut slot, &params, &master, 0.8, 9600);
+            let spectrum = analysis::spectrum(&out, SR, 256);
+            let total = analysis::band_energy(&spectrum, SR, 0.0, SR * 0.5);
+            let body = slot.base_freq;
+            (
+                analysis::band_energy(&spectrum, SR, 6000.0, SR * 0.5) / total,
+                analysis::band_energy(&spectrum, SR, body * 0.8, body * 1.25) / total,
+            )
+        };
+        let ((dark, thick), (bright, thin)) = (shares(true, 0.2), shares(true, 0.8));
+        let ((narrow_dark, narrow_thick), (narrow_bright, narrow_thin)) =
+            (shares(false, 0.2), shares(false, 0.8));
+        // Turning tone up brightens the hit and thins its body...
+        assert!(bright > dark && thin < thick);
+        // ...and further on both counts than the narrow mapping
+        assert!(
+            bright / dark > 1.2 * (narrow_bright / narrow_dark),
+            "brightness x{} vs x{}",
+            bright / dark,
+            narrow_bright / narrow_dark
+        );
+        assert!(
+            thick / thin > 1.2 * (narrow_thick / narrow_thin),
+            "body x{} vs x{}",
+            thick / thin,
+            narrow_thick / narrow_thin
+        );
+    }
+
+    #[test]
+    fn body_drive_and_click_move_independently() {
+        let master = master();
+        // A clean kick (no slot saturator) with snap out of the way
+        let render = |body_drive: f32, click: f32| {
+            let params = slot_params(DrumSlotParams::default_kick, |p| {
+                p.snap = float(0.0, 0.0, 1.0);
+                p.clean = BoolParam::new("Test", true);
+                p.body_drive = float(body_drive, -1.0, 1.0);
+                p.click = float(click, -1.0, 1.0);
+            });
+            hit(
+                &mut DrumSlot::new(SlotType::Kick, SR),
+                &params,
+                &master,
+                1.0,
+                9600,
+            )
+        };
+        let minus = |a: &[f32], b: &[f32]| a.iter().zip(b).map(|(a, b)| a - b).collect::<Vec<_>>();
+        // Overtones of the ringing body, against its total
+        let grit = |x: &[f32]| {
+            let spectrum = analysis::spectrum(&x[2400..], SR, 256);
+            analysis::band_energy(&spectrum, SR, 300.0, 4000.0)
+                / analysis::band_energy(&spectrum, SR, 0.0, SR * 0.5)
+        };
+
+        // Body drive dirties the body and leaves the click (what it adds on top)
+        // exactly as it was
+        let (clean_body, driven_body) = (render(0.0, 0.0), render(1.0, 0.0));
+        assert!(grit(&driven_body) > 3.0 * grit(&clean_body));
+        let click = minus(&render(0.0, 1.0), &clean_body);
+        let driven_click = minus(&render(1.0, 1.0), &driven_body);
+        assert!(rms(&click) > 0.01);
+        assert!(
+            click
+                .iter()
+                .zip(&driven_click)
+                .all(|(a, b)| (a - b).abs() < 1e-5)
+        );
+    }
+
+    #[test]
+    fn max_pitch_holds_even_at_the_top_of_every_offset() {
+        // Kit pitch, slot pitch and the note all as high as they go
+        let master = MasterParams {
+            kit_pitch: float(12.0, -12.0, 12.0),
+            ..MasterParams::default()
+        };
+        settle_smoothers(&master);
+        for (kind, defaults) in [
+            (SlotType::Kick, DrumSlotParams::default_kick as fn() -> _),
+            (SlotType::Perc1, DrumSlotParams::default_perc1),
+        ] {
+            // The body's frequency each sample, from its phase steps
+            let freqs = |ceiling: f32| {
+                let params = slot_params(defaults, |p| {
+                    p.pitch = float(24.0, -24.0, 24.0);
+                    p.max_pitch = float(ceiling, 100.0, PITCH_MAX_HZ);
+                });
+                let mut slot = DrumSlot::new(kind, SR);
+                slot.trigger(1.0, 12.0, &params, &master);
+                assert!(slot.base_freq <= ceiling);
+                (0..4800)
+                    .map(|_| {
+                        let before = slot.osc_phase;
+                        slot.process(&params, &master, None);
+                        (slot.osc_phase - before).rem_euclid(2.0 * PI) * SR / (2.0 * PI)
+                    })
+                    .fold(0.0, f32::max)
+            };
+            let (capped, open) = (freqs(300.0), freqs(PITCH_MAX_HZ));
+            assert!(capped <= 300.0 * 1.001, "{capped}");
+            assert!(open > 1000.0, "{open}");
+        }
+    }
+
+    #[test]
+    fn freeze_humanize_loops_the_captured_hits_in_order() {
+        let master = |freeze: bool| {
+            let master = MasterParams {
+                freeze_humanize: BoolParam::new("Test", freeze),
+                ..MasterParams::default()
+            };
+            settle_smoothers(&master);
+            master
+        };
+        let (live, frozen) = (master(false), master(true));
+        let params = slot_params(DrumSlotParams::default_snare, |p| {
+            p.humanize = float(1.0, 0.0, 1.0);
+        });
+        let mut slot = DrumSlot::new(SlotType::Snare, SR);
+        let mut play = |master: &MasterParams| {
+            slot.trigger(1.0, 0.0, &params, master);
+            (slot.human_amp, slot.human_pitch, slot.human_decay_mul)
+        };
+
+        // Five live hits, each humanized differently
+        let take: Vec<_> = (0..5).map(|_| play(&live)).collect();
+        assert!(take.windows(2).all(|w| w[0] != w[1]));
+
+        // Frozen, the take plays back in order, round and round
+        for i in 0..12 {
+            assert_eq!(play(&frozen), take[i % 5], "hit {i}");
+        }
+        // Back live, fresh values again
+        assert!(!take.contains(&play(&live)));
     }
 }
@@ -12,6 +12,355 @@ pub fn flush_denormals(x: f32) -> f32 {
     if x.abs() < 1e-24 { 0.0 } else { x }
 }
 
+/// `fast_tanh` for the f64 master chain.
+#[inline]
+pub fn fast_tanh_f64(x: f64) -> f64 {
+    let x2 = x * x;
+    x * (27.0 + x2) / (27.0 + 9.0 * x2)
+}
+
+/// `flush_denormals` for the f64 master chain.
+#[inline]
+pub fn flush_denormals_f64(x: f64) -> f64 {
+    if x.abs() < 1e-24 { 0.0 } else { x }
+}
+
+/// Advance the shared LCG and return a uniform value in -1..1.
+#[inline]
+pub fn lcg_bipolar(state: &mut u32) -> f32 {
+    *state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+    let bits = 0x3F800000 | (*state >> 9);
+    let f = f32::from_bits(bits) - 1.0;
+    f * 2.0 - 1.0
+}
+
+/// Triangular (TPDF) dither noise spanning ±`lsb`.
+#[inline]
+pub fn tpdf_dither(state: &mut u32, lsb: f32) -> f32 {
+    (lcg_bipolar(state) + lcg_bipolar(state)) * 0.5 * lsb
+}
+
+/// Bend a 0..1 macro around its midpoint. `curve` 0 is linear; positive values
+/// spend more of the travel at the top end, negative at the bottom. Endpoints stay put.
+#[inline]
+pub fn shape_macro(x: f32, curve: f32) -> f32 {
+    let x = x.clamp(0.0, 1.0);
+    let curve = curve.clamp(-1.0, 1.0);
+    if curve.abs() < 1e-4 {
+        return x;
+    }
+    x.powf(2.0f32.powf(-2.0 * curve))
+}
+
+/// Unity below `knee`, then eases into a hard ceiling at `ceiling` with matching slope.
+#[inline]
+pub fn soft_knee(x: f64, knee: f64, ceiling: f64) -> f64 {
+    let a = x.abs();
+    if a <= knee {
+        return x;
+    }
+    let room = (ceiling - knee).max(1e-6);
+    // fast_tanh reaches 1 with zero slope at 3 and overshoots past it
+    (knee + room * fast_tanh_f64(((a - knee) / room).min(3.0))).copysign(x)
+}
+
+/// Snap a pitch in semitones to the nearest degree of `scale` (semitones above the
+/// root, within one octave). An empty scale leaves the pitch untouched.
+#[inline]
+pub fn quantize_to_scale(semis: f32, scale: &[f32]) -> f32 {
+    if scale.is_empty() {
+        return semis;
+    }
+    let octave = (semis / 12.0).floor() * 12.0;
+    let within = semis - octave;
+    let mut best = 0.0;
+    for &d in scale.iter().chain(core::iter::once(&12.0)) {
+        if (d - within).abs() < (best - within).abs() {
+            best = d;
+        }
+    }
+    octave + best
+}
+
+/// Zeroed buffer of `len` values, or `None` if it can't be allocated.
+pub fn try_zeroed<T: Copy + Default>(len: usize) -> Option<Vec<T>> {
+    let mut buf = Vec::new();
+    buf.try_reserve_exact(len).ok()?;
+    buf.resize(len, T::default());
+    Some(buf)
+}
+
+/// Delay line with a fixed maximum length and a fractional read. If its buffer
+/// can't be allocated it stays empty and passes the input straight through.
+pub struct DelayLine {
+    buf: Vec<f32>,
+    idx: usize,
+}
+
+impl DelayLine {
+    pub fn new(max_delay: usize) -> Self {
+        Self {
+            buf: try_zeroed(max_delay.saturating_add(1)).unwrap_or_default(),
+            idx: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for x in &mut self.buf {
+            *x = 0.0;
+        }
+        self.idx = 0;
+    }
+
+    /// Write `x` and return the signal `delay` samples ago (clamped to the max),
+    /// read between samples so the delay can move without jumping.
+    #[inline]
+    pub fn process(&mut self, x: f32, delay: f32) -> f32 {
+        let len = self.buf.len();
+        if len == 0 {
+            return x;
+        }
+        self.buf[self.idx] = x;
+        let d = delay.clamp(0.0, (len - 1) as f32);
+        let whole = d as usize;
+        let frac = d - whole as f32;
+        let a = self.buf[(self.idx + len - whole) % len];
+        let b = self.buf[(self.idx + 2 * len - whole - 1) % len];
+        self.idx = (self.idx + 1) % len;
+        a + (b - a) * frac
+    }
+}
+
+/// Schroeder allpass: flat magnitude, smeared phase. The delay is fixed at construction;
+/// if its buffer can't be allocated it passes the input straight through.
+pub struct Allpass {
+    buf: Vec<f32>,
+    idx: usize,
+    g: f32,
+}
+
+impl Allpass {
+    pub fn new(delay: usize, g: f32) -> Self {
+        Self {
+            buf: try_zeroed(delay.max(1)).unwrap_or_default(),
+            idx: 0,
+            g,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for x in &mut self.buf {
+            *x = 0.0;
+        }
+        self.idx = 0;
+    }
+
+    #[inline]
+    pub fn process(&mut self, x: f32) -> f32 {
+        if self.buf.is_empty() {
+            return x;
+        }
+        let delayed = self.buf[self.idx];
+        let w = flush_denormals(x + self.g * delayed);
+        self.buf[self.idx] = w;
+        self.idx = (self.idx + 1) % self.buf.len();
+        delayed - self.g * w
+    }
+}
+
+/// RBJ-cookbook biquad, transposed direct form II. Coefficients and state are
+/// f64, so it serves the f64 master chain as well as the f32 voices.
+#[derive(Clone, Copy)]
+pub struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Default for Biquad {
+    fn default() -> Self {
+        Self {
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+}
+
+impl Biquad {
+    fn set_coeffs(&mut self, b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) {
+        let inv = 1.0 / a0 as f64;
+        self.b0 = b0 as f64 * inv;
+        self.b1 = b1 as f64 * inv;
+        self.b2 = b2 as f64 * inv;
+        self.a1 = a1 as f64 * inv;
+        self.a2 = a2 as f64 * inv;
+    }
+
+    fn omega(sr: f32, freq: f32) -> (f32, f32) {
+        let w0 = 2.0 * PI * freq.clamp(10.0, sr * 0.45) / sr;
+        (w0.cos(), w0.sin())
+    }
+
+    /// Shelf below `freq` (slope 1).
+    pub fn low_shelf(&mut self, sr: f32, freq: f32, gain_db: f32) {
+        let a = 10.0f32.powf(gain_db / 40.0);
+        let (cos, sin) = Self::omega(sr, freq);
+        let k = 2.0 * a.sqrt() * sin / core::f32::consts::SQRT_2;
+        self.set_coeffs(
+            a * ((a + 1.0) - (a - 1.0) * cos + k),
+            2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+            a * ((a + 1.0) - (a - 1.0) * cos - k),
+            (a + 1.0) + (a - 1.0) * cos + k,
+            -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+            (a + 1.0) + (a - 1.0) * cos - k,
+        );
+    }
+
+    /// Shelf above `freq` (slope 1).
+    pub fn high_shelf(&mut self, sr: f32, freq: f32, gain_db: f32) {
+        let a = 10.0f32.powf(gain_db / 40.0);
+        let (cos, sin) = Self::omega(sr, freq);
+        let k = 2.0 * a.sqrt() * sin / core::f32::consts::SQRT_2;
+        self.set_coeffs(
+            a * ((a + 1.0) + (a - 1.0) * cos + k),
+            -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+            a * ((a + 1.0) + (a - 1.0) * cos - k),
+            (a + 1.0) - (a - 1.0) * cos + k,
+            2.0 * ((a - 1.0) - (a + 1.0) * cos),
+            (a + 1.0) - (a - 1.0) * cos - k,
+        );
+    }
+
+    /// Peaking bell at `freq`.
+    pub fn peak(&mut self, sr: f32, freq: f32, q: f32, gain_db: f32) {
+        let a = 10.0f32.powf(gain_db / 40.0);
+        let (cos, sin) = Self::omega(sr, freq);
+        let alpha = sin / (2.0 * q.max(0.05));
+        self.set_coeffs(
+            1.0 + alpha * a,
+            -2.0 * cos,
+            1.0 - alpha * a,
+            1.0 + alpha / a,
+            -2.0 * cos,
+            1.0 - alpha / a,
+        );
+    }
+
+    /// 12 dB/oct high-pass at `freq`.
+    pub fn high_pass(&mut self, sr: f32, freq: f32, q: f32) {
+        let (cos, sin) = Self::omega(sr, freq);
+        let alpha = sin / (2.0 * q.max(0.05));
+        self.set_coeffs(
+            (1.0 + cos) * 0.5,
+            -(1.0 + cos),
+            (1.0 + cos) * 0.5,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        );
+    }
+
+    /// 12 dB/oct low-pass at `freq`.
+    pub fn low_pass(&mut self, sr: f32, freq: f32, q: f32) {
+        let (cos, sin) = Self::omega(sr, freq);
+        let alpha = sin / (2.0 * q.max(0.05));
+        self.set_coeffs(
+            (1.0 - cos) * 0.5,
+            1.0 - cos,
+            (1.0 - cos) * 0.5,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        );
+    }
+
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+
+    #[inline]
+    pub fn process(&mut self, x: f32) -> f32 {
+        self.process_f64(x as f64) as f32
+    }
+
+    #[inline]
+    pub fn process_f64(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        flush_denormals_f64(y)
+    }
+}
+
+/// Low shelf / mid bell / high shelf with gains in dB. Bypassed while flat.
+pub struct ThreeBandEq {
+    sr: f32,
+    gains: [f32; 3],
+    low: Biquad,
+    mid: Biquad,
+    high: Biquad,
+}
+
+impl ThreeBandEq {
+    const LOW_HZ: f32 = 200.0;
+    const MID_HZ: f32 = 1000.0;
+    const MID_Q: f32 = 0.7;
+    const HIGH_HZ: f32 = 5000.0;
+
+    pub fn new(sr: f32) -> Self {
+        Self {
+            sr,
+            gains: [0.0; 3],
+            low: Biquad::default(),
+            mid: Biquad::default(),
+            high: Biquad::default(),
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sr: f32) {
+        self.sr = sr;
+        self.update();
+    }
+
+    fn update(&mut self) {
+        let [low, mid, high] = self.gains;
+        self.low.low_shelf(self.sr, Self::LOW_HZ, low);
+        self.mid.peak(self.sr, Self::MID_HZ, Self::MID_Q, mid);
+        self.high.high_shelf(self.sr, Self::HIGH_HZ, high);
+        if self.gains == [0.0; 3] {
+            // Bypassed: don't resume later from stale state.
+            self.low.reset();
+            self.mid.reset();
+            self.high.reset();
+        }
+    }
+
+    #[inline]
+    pub fn process(&mut self, x: f32, low_db: f32, mid_db: f32, high_db: f32) -> f32 {
+        let gains = [low_db, mid_db, high_db];
+        if gains != self.gains {
+            self.gains = gains;
+            self.update();
+        }
+        if gains == [0.0; 3] {
+            return x;
+        }
+        let y = self.low.process(x);
+        let y = self.mid.process(y);
+        self.high.process(y)
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum Wave { Sine, Saw, Square }
 
@@ -128,3 +477,172 @@ impl ZdfSvf {
         }
     }
 }
+
+/// Offline spectral helpers for regression checks. Allocates; test builds only.
+#[cfg(test)]
+pub(crate) mod analysis {
+    /// Goertzel magnitude of `signal` at `freq`; a full-scale sine reads ~1.0.
+    pub(crate) fn goertzel(signal: &[f32], sr: f32, freq: f32) -> f32 {
+        if signal.is_empty() {
+            return 0.0;
+        }
+        let w = 2.0 * std::f64::consts::PI * freq as f64 / sr as f64;
+        let coeff = 2.0 * w.cos();
+        let (mut s1, mut s2) = (0.0f64, 0.0f64);
+        for &x in signal {
+            let s0 = x as f64 + coeff * s1 - s2;
+            s2 = s1;
+            s1 = s0;
+        }
+        let power = (s1 * s1 + s2 * s2 - coeff * s1 * s2).max(0.0);
+        (power.sqrt() * 2.0 / signal.len() as f64) as f32
+    }
+
+    /// Magnitudes at `n_bins` evenly spaced frequencies from DC up to Nyquist.
+    pub(crate) fn spectrum(signal: &[f32], sr: f32, n_bins: usize) -> Vec<f32> {
+        let step = sr * 0.5 / n_bins.max(1) as f32;
+        (0..n_bins)
+            .map(|k| goertzel(signal, sr, k as f32 * step))
+            .collect()
+    }
+
+    /// Frequency of bin `k` in a `spectrum` of `n_bins`.
+    pub(crate) fn bin_freq(k: usize, sr: f32, n_bins: usize) -> f32 {
+        k as f32 * sr * 0.5 / n_bins.max(1) as f32
+    }
+
+    /// Summed power of the `spectrum` bins between `lo` and `hi` Hz.
+    pub(crate) fn band_energy(spectrum: &[f32], sr: f32, lo: f32, hi: f32) -> f32 {
+        let n = spectrum.len();
+        spectrum
+            .iter()
+            .enumerate()
+            .filter(|&(k, _)| (lo..hi).contains(&bin_freq(k, sr, n)))
+            .map(|(_, m)| m * m)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, sr: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * PI * freq * i as f32 / sr).sin())
+            .collect()
+    }
+
+    #[test]
+    fn spectrum_peaks_at_the_sine_bin() {
+        let (sr, n_bins) = (48000.0, 64);
+        let freq = analysis::bin_freq(10, sr, n_bins);
+        // A whole number of cycles, so nothing leaks into the other bins
+        let spectrum = analysis::spectrum(&sine(freq, sr, 4800), sr, n_bins);
+
+        let peak = (0..n_bins)
+            .max_by(|&a, &b| spectrum[a].total_cmp(&spectrum[b]))
+            .unwrap();
+        assert_eq!(peak, 10);
+        assert!(
+            (spectrum[peak] - 1.0).abs() < 0.01,
+            "full-scale sine read {}",
+            spectrum[peak]
+        );
+
+        let total = analysis::band_energy(&spectrum, sr, 0.0, sr);
+        let around = analysis::band_energy(&spectrum, sr, freq - 100.0, freq + 100.0);
+        assert!(around > 0.99 * total);
+    }
+
+    #[test]
+    fn shaped_macro_keeps_its_range_but_moves_the_midpoint() {
+        for curve in [-1.0, -0.5, 0.5, 1.0] {
+            assert_eq!(shape_macro(0.0, curve), 0.0);
+            assert!((shape_macro(1.0, curve) - 1.0).abs() < 1e-6);
+            let mid = shape_macro(0.5, curve);
+            assert!(
+                (mid - 0.5).abs() > 0.1,
+                "curve {curve} left the midpoint at {mid}"
+            );
+            // Positive curves spend more of the travel at the top end, so the middle reads high
+            assert_eq!(mid > 0.5, curve > 0.0);
+            let steps: Vec<f32> = (0..=20)
+                .map(|i| shape_macro(i as f32 / 20.0, curve))
+                .collect();
+            assert!(steps.windows(2).all(|w| w[1] > w[0]));
+        }
+        // Flat curve is the old linear response
+        for i in 0..=10 {
+            let x = i as f32 / 10.0;
+            assert_eq!(shape_macro(x, 0.0), x);
+        }
+    }
+
+    #[test]
+    fn soft_knee_is_transparent_below_the_knee_and_bounded_above() {
+        let curve: Vec<f64> = (0..=1000)
+            .map(|i| soft_knee(i as f64 * 0.1, 1.0, 2.0))
+            .collect();
+        assert!(
+            curve[..=10]
+                .iter()
+                .enumerate()
+                .all(|(i, &y)| y == i as f64 * 0.1)
+        );
+        assert!(curve.windows(2).all(|w| w[1] >= w[0]));
+        assert!(curve.iter().all(|&y| y <= 2.0));
+        assert_eq!(soft_knee(-50.0, 1.0, 2.0), -2.0);
+    }
+
+    #[test]
+    fn dither_decorrelates_quantization_error() {
+        let (sr, lsb) = (48000.0, 1.0 / 256.0);
+        let quantize = |x: f32| (x / lsb).round() * lsb;
+        let input: Vec<f32> = sine(1000.0, sr, 48000)
+            .iter()
+            .map(|x| x * 1.5 * lsb)
+            .collect();
+
+        let mut state = 1;
+        let noise: Vec<f32> = (0..input.len())
+            .map(|_| tpdf_dither(&mut state, lsb))
+            .collect();
+        let plain: Vec<f32> = input.iter().map(|&x| quantize(x)).collect();
+        let dithered: Vec<f32> = input
+            .iter()
+            .zip(&noise)
+            .map(|(&x, &n)| quantize(x + n))
+            .collect();
+
+        // Low level: within ±1 LSB, mean-free, about lsb/sqrt(6) RMS and white
+        let len = noise.len() as f32;
+        let rms = (noise.iter().map(|n| n * n).sum::<f32>() / len).sqrt();
+        assert!(noise.iter().all(|n| n.abs() <= lsb));
+        assert!((noise.iter().sum::<f32>() / len).abs() < 0.01 * lsb);
+        assert!(
+            (rms / lsb - 6f32.sqrt().recip()).abs() < 0.02,
+            "dither rms {} LSB",
+            rms / lsb
+        );
+        let lag1 = noise.windows(2).map(|w| w[0] * w[1]).sum::<f32>() / (len * rms * rms);
+        assert!(
+            lag1.abs() < 0.02,
+            "successive dither samples correlate by {lag1}"
+        );
+
+        let harmonics = |signal: &[f32]| -> f32 {
+            (2..=5)
+                .map(|h| analysis::goertzel(signal, sr, 1000.0 * h as f32))
+                .sum()
+        };
+        let (before, after) = (harmonics(&plain), harmonics(&dithered));
+        assert!(
+            after < 0.25 * before,
+            "harmonics {before} undithered vs {after} dithered"
+        );
+        // The fundamental itself survives
+        let fundamental = analysis::goertzel(&dithered, sr, 1000.0);
+        assert!((fundamental / (1.5 * lsb) - 1.0).abs() < 0.05);
+    }
+}
@@ -1,128 +1,216 @@
 use crate::drum_engine::N_SLOTS;
-use crate::params::{DrumParams, DrumSlotParams, MasterParams};
+use crate::params::DrumParams;
+use nih_plug::prelude::{FloatParam, Param, ParamPtr};
+use std::collections::BTreeMap;
 
+/// Per-slot macros: level, pan, tone, decay (ms), snap, pitch (st), humanize.
+pub const SLOT_MACROS: usize = 7;
+pub type SlotValues = [f32; SLOT_MACROS];
+
+/// Master macros: drive, comp, reverb, kit pitch (st), velocity curve.
+pub const MASTER_MACROS: usize = 5;
+pub type MasterValues = [f32; MASTER_MACROS];
+
+/// How many user kits the plugin state keeps.
+pub const N_USER_KITS: usize = 4;
+
+/// A user kit: every parameter's plain value by id, as heard when it was saved, so
+/// recalling one sets the host's values like a preset. The Kit selector is left
+/// out, so a recall never re-applies a factory kit over the values it restores.
+pub type KitSnapshot = BTreeMap<String, f32>;
+
+/// A factory kit laid over the host's values: the kit index, then per macro the
+/// host value the kit covers, or `None` once the host has moved that macro itself.
+pub type KitOverride = (
+    i32,
+    [[Option<f32>; SLOT_MACROS]; N_SLOTS],
+    [Option<f32>; MASTER_MACROS],
+);
+
+/// A/B compare: the live side (0 = A, 1 = B) and what each side holds.
+pub type AbCompare = (usize, [Option<KitSnapshot>; 2]);
+
+/// Glide every slot and the master section towards the given tables. RT-safe: only
+/// the parameters' smoothers are retargeted, the host-facing values are untouched.
+pub fn apply_values(
+    params: &DrumParams,
+    slots: &[SlotValues; N_SLOTS],
+    master: &MasterValues,
+    sample_rate: f32,
+) {
+    for (i, values) in slots.iter().enumerate() {
+        params.slot(i).glide_to(values, sample_rate);
+    }
+    params.master.glide_to(master, sample_rate);
+}
+
+/// Let go of every macro the host has moved since the kit went in; the wrapper
+/// has already pointed its smoother at the host's value.
+pub fn release_moved(ov: &mut KitOverride, params: &DrumParams) {
+    for_each_macro(ov, params, |param, _, covered| {
+        if covered.is_some_and(|host| host != param.value()) {
+            *covered = None;
+        }
+    });
+}
+
+/// Put the macros still under `ov` back on the kit, after the wrapper reset their
+/// smoothers to the host's values (activation, state load).
+pub fn reapply(ov: &mut KitOverride, params: &DrumParams) {
+    release_moved(ov, params);
+    for_each_macro(ov, params, |param, value, covered| {
+        if covered.is_some() {
+            param.smoothed.reset(value);
+        }
+    });
+}
+
+/// Hand every macro still under `ov` back to the host, gliding over to its value.
+pub fn release_all(ov: &mut KitOverride, params: &DrumParams, sample_rate: f32) {
+    for_each_macro(ov, params, |param, _, covered| {
+        if covered.take().is_some() {
+            param.smoothed.set_target(sample_rate, param.value());
+        }
+    });
+}
+
+/// The macros still under `ov`, with the kit's value for each. Allocates.
+pub fn covered(ov: &KitOverride, params: &DrumParams) -> Vec<(ParamPtr, f32)> {
+    let mut ov = *ov;
+    let mut held = Vec::new();
+    for_each_macro(&mut ov, params, |param, value, covered| {
+        if covered.is_some() {
+            held.push((param.as_ptr(), value));
+        }
+    });
+    held
+}
+
+/// Visit every macro with the kit's value for it and its entry in `ov`. An
+/// override for a kit that no longer exists covers nothing.
+fn for_each_macro(
+    ov: &mut KitOverride,
+    params: &DrumParams,
+    mut f: impl FnMut(&FloatParam, f32, &mut Option<f32>),
+) {
+    let (index, slots, master) = ov;
+    let Some(kit) = FACTORY_KITS.get(*index as usize) else {
+        *slots = [[None; SLOT_MACROS]; N_SLOTS];
+        *master = [None; MASTER_MACROS];
+        return;
+    };
+    for (i, covered) in slots.iter_mut().enumerate() {
+        for ((param, &value), covered) in params
+            .slot(i)
+            .macros()
+            .into_iter()
+            .zip(&kit.slots[i])
+            .zip(covered)
+        {
+            f(param, value, covered);
+        }
+    }
+    for ((param, &value), covered) in params
+        .master
+        .macros()
+        .into_iter()
+        .zip(&kit.master)
+        .zip(master)
+    {
+        f(param, value, covered);
+    }
+}
+
+/// A factory kit as plain values, so applying one never allocates.
 pub struct Kit<'a> {
     pub name: &'a str,
-    pub make: fn() -> DrumParams,
+    /// Kick, Snare, Clap, Closed Hat, Open Hat, Tom, Perc 1, Perc 2
+    pub slots: [SlotValues; N_SLOTS],
+    pub master: MasterValues,
+}
+
+impl Kit<'_> {
+    /// Glide towards this kit, see [`apply_values`], and return the override that
+    /// keeps it in force over the host values it covers.
+    pub fn apply(&self, index: i32, params: &DrumParams, sample_rate: f32) -> KitOverride {
+        apply_values(params, &self.slots, &self.master, sample_rate);
+        (
+            index,
+            std::array::from_fn(|i| params.slot(i).macros().map(|p| Some(p.value()))),
+            params.master.macros().map(|p| Some(p.value())),
+        )
+    }
 }
 
 pub const FACTORY_KITS: &[Kit<'_>] = &[
     Kit {
         name: "Init",
-        make: kit_init,
+        slots: [
+            [0.9, 0.0, 0.4, 300.0, 0.6, 0.0, 0.2],
+            [0.9, 0.0, 0.6, 200.0, 0.7, 0.0, 0.2],
+            [0.8, 0.0, 0.7, 180.0, 0.8, 0.0, 0.2],
+            [0.7, -0.1, 0.8, 80.0, 0.5, 0.0, 0.1],
+            [0.7, -0.1, 0.8, 450.0, 0.4, 0.0, 0.1],
+            [0.8, 0.1, 0.5, 260.0, 0.4, 0.0, 0.1],
+            [0.7, 0.2, 0.7, 220.0, 0.5, 0.0, 0.2],
+            [0.7, 0.3, 0.5, 220.0, 0.5, 0.0, 0.2],
+        ],
+        master: [0.1, 0.3, 0.2, 0.0, 0.5],
     },
     Kit {
         name: "808 Clean",
-        make: kit_808_clean,
+        slots: [
+            [1.0, 0.0, 0.40, 360.0, 0.55, -2.0, 0.10],
+            [0.9, 0.0, 0.65, 220.0, 0.75, 0.0, 0.20],
+            [0.8, 0.0, 0.75, 190.0, 0.85, 0.0, 0.20],
+            [0.65, -0.1, 0.85, 70.0, 0.50, 0.0, 0.10],
+            [0.7, -0.1, 0.85, 320.0, 0.40, 0.0, 0.10],
+            [0.8, 0.05, 0.55, 260.0, 0.40, -2.0, 0.10],
+            [0.7, 0.2, 0.70, 220.0, 0.50, 0.0, 0.20],
+            [0.7, 0.3, 0.55, 220.0, 0.50, 0.0, 0.20],
+        ],
+        master: [0.15, 0.25, 0.15, 0.0, 0.45],
     },
     Kit {
         name: "EDM Punch",
-        make: kit_edm_punch,
+        slots: [
+            [1.1, 0.0, 0.55, 280.0, 0.85, 0.0, 0.15],
+            [1.0, 0.0, 0.75, 190.0, 0.85, 2.0, 0.20],
+            [0.9, 0.0, 0.80, 200.0, 0.90, 0.0, 0.15],
+            [0.75, -0.2, 0.90, 90.0, 0.60, 0.0, 0.10],
+            [0.8, -0.2, 0.90, 380.0, 0.50, 0.0, 0.10],
+            [0.85, 0.1, 0.60, 260.0, 0.45, 0.0, 0.10],
+            [0.8, 0.25, 0.75, 240.0, 0.60, 2.0, 0.20],
+            [0.8, 0.35, 0.65, 240.0, 0.55, -2.0, 0.20],
+        ],
+        master: [0.35, 0.55, 0.20, 0.0, 0.55],
     },
     Kit {
         name: "Minimal Tech",
-        make: kit_minimal_tech,
+        slots: [
+            [1.0, 0.0, 0.35, 260.0, 0.65, -1.0, 0.15],
+            [0.8, 0.05, 0.55, 170.0, 0.65, -2.0, 0.15],
+            [0.75, 0.1, 0.65, 160.0, 0.70, 0.0, 0.20],
+            [0.65, -0.2, 0.75, 70.0, 0.50, 0.0, 0.10],
+            [0.7, -0.25, 0.75, 320.0, 0.45, 0.0, 0.10],
+            [0.75, 0.15, 0.45, 230.0, 0.35, -1.0, 0.10],
+            [0.65, 0.2, 0.60, 220.0, 0.50, 0.0, 0.15],
+            [0.65, 0.3, 0.55, 220.0, 0.45, 0.0, 0.15],
+        ],
+        master: [0.25, 0.40, 0.10, 0.0, 0.45],
     },
     Kit {
         name: "Lo-Fi",
-        make: kit_lofi,
+        slots: [
+            [0.9, -0.05, 0.30, 240.0, 0.40, -3.0, 0.25],
+            [0.85, 0.05, 0.40, 210.0, 0.50, -4.0, 0.30],
+            [0.8, 0.0, 0.50, 190.0, 0.55, -2.0, 0.30],
+            [0.6, -0.1, 0.55, 90.0, 0.40, -4.0, 0.20],
+            [0.65, -0.1, 0.55, 420.0, 0.35, -4.0, 0.20],
+            [0.7, 0.1, 0.45, 260.0, 0.40, -3.0, 0.20],
+            [0.75, 0.15, 0.50, 260.0, 0.45, -2.0, 0.30],
+            [0.75, 0.25, 0.45, 260.0, 0.45, -4.0, 0.30],
+        ],
+        master: [0.55, 0.35, 0.30, -1.0, 0.40],
     },
 ];
-
-fn kit_init() -> DrumParams {
-    DrumParams::default()
-}
-
-fn kit_808_clean() -> DrumParams {
-    DrumParams {
-        kick: DrumSlotParams::from_values("Kick", 1.0, 0.0, 0.40, 360.0, 0.55, -2.0, 0.10),
-        snare: DrumSlotParams::from_values("Snare", 0.9, 0.0, 0.65, 220.0, 0.75, 0.0, 0.20),
-        clap: DrumSlotParams::from_values("Clap", 0.8, 0.0, 0.75, 190.0, 0.85, 0.0, 0.20),
-        hat_closed: DrumSlotParams::from_values(
-            "Hat Closed",
-            0.65,
-            -0.1,
-            0.85,
-            70.0,
-            0.50,
-            0.0,
-            0.10,
-        ),
-        hat_open: DrumSlotParams::from_values("Hat Open", 0.7, -0.1, 0.85, 320.0, 0.40, 0.0, 0.10),
-        tom: DrumSlotParams::from_values("Tom", 0.8, 0.05, 0.55, 260.0, 0.40, -2.0, 0.10),
-        perc1: DrumSlotParams::from_values("Perc1", 0.7, 0.2, 0.70, 220.0, 0.50, 0.0, 0.20),
-        perc2: DrumSlotParams::from_values("Perc2", 0.7, 0.3, 0.55, 220.0, 0.50, 0.0, 0.20),
-        master: MasterParams::from_values(0.15, 0.25, 0.15, 0.0, 0.45),
-    }
-}
-
-fn kit_edm_punch() -> DrumParams {
-    DrumParams {
-        kick: DrumSlotParams::from_values("Kick", 1.1, 0.0, 0.55, 280.0, 0.85, 0.0, 0.15),
-        snare: DrumSlotParams::from_values("Snare", 1.0, 0.0, 0.75, 190.0, 0.85, 2.0, 0.20),
-        clap: DrumSlotParams::from_values("Clap", 0.9, 0.0, 0.80, 200.0, 0.90, 0.0, 0.15),
-        hat_closed: DrumSlotParams::from_values(
-            "Hat Closed",
-            0.75,
-            -0.2,
-            0.90,
-            90.0,
-            0.60,
-            0.0,
-            0.10,
-        ),
-        hat_open: DrumSlotParams::from_values("Hat Open", 0.8, -0.2, 0.90, 380.0, 0.50, 0.0, 0.10),
-        tom: DrumSlotParams::from_values("Tom", 0.85, 0.1, 0.60, 260.0, 0.45, 0.0, 0.10),
-        perc1: DrumSlotParams::from_values("Perc1", 0.8, 0.25, 0.75, 240.0, 0.60, 2.0, 0.20),
-        perc2: DrumSlotParams::from_values("Perc2", 0.8, 0.35, 0.65, 240.0, 0.55, -2.0, 0.20),
-        master: MasterParams::from_values(0.35, 0.55, 0.20, 0.0, 0.55),
-    }
-}
-
-fn kit_minimal_tech() -> DrumParams {
-    DrumParams {
-        kick: DrumSlotParams::from_values("Kick", 1.0, 0.0, 0.35, 260.0, 0.65, -1.0, 0.15),
-        snare: DrumSlotParams::from_values("Snare", 0.8, 0.05, 0.55, 170.0, 0.65, -2.0, 0.15),
-        clap: DrumSlotParams::from_values("Clap", 0.75, 0.1, 0.65, 160.0, 0.70, 0.0, 0.20),
-        hat_closed: DrumSlotParams::from_values(
-            "Hat Closed",
-            0.65,
-            -0.2,
-            0.75,
-            70.0,
-            0.50,
-            0.0,
-            0.10,
-        ),
-        hat_open: DrumSlotParams::from_values("Hat Open", 0.7, -0.25, 0.75, 320.0, 0.45, 0.0, 0.10),
-        tom: DrumSlotParams::from_values("Tom", 0.75, 0.15, 0.45, 230.0, 0.35, -1.0, 0.10),
-        perc1: DrumSlotParams::from_values("Perc1", 0.65, 0.2, 0.60, 220.0, 0.50, 0.0, 0.15),
-        perc2: DrumSlotParams::from_values("Perc2", 0.65, 0.3, 0.55, 220.0, 0.45, 0.0, 0.15),
-        master: MasterParams::from_values(0.25, 0.40, 0.10, 0.0, 0.45),
-    }
-}
-
-fn kit_lofi() -> DrumParams {
-    DrumParams {
-        kick: DrumSlotParams::from_values("Kick", 0.9, -0.05, 0.30, 240.0, 0.40, -3.0, 0.25),
-        snare: DrumSlotParams::from_values("Snare", 0.85, 0.05, 0.40, 210.0, 0.50, -4.0, 0.30),
-        clap: DrumSlotParams::from_values("Clap", 0.8, 0.0, 0.50, 190.0, 0.55, -2.0, 0.30),
-        hat_closed: DrumSlotParams::from_values(
-            "Hat Closed",
-            0.6,
-            -0.1,
-            0.55,
-            90.0,
-            0.40,
-            -4.0,
-            0.20,
-        ),
-        hat_open: DrumSlotParams::from_values(
-            "Hat Open", 0.65, -0.1, 0.55, 420.0, 0.35, -4.0, 0.20,
-        ),
-        tom: DrumSlotParams::from_values("Tom", 0.7, 0.1, 0.45, 260.0, 0.40, -3.0, 0.20),
-        perc1: DrumSlotParams::from_values("Perc1", 0.75, 0.15, 0.50, 260.0, 0.45, -2.0, 0.30),
-        perc2: DrumSlotParams::from_values("Perc2", 0.75, 0.25, 0.45, 260.0, 0.45, -4.0, 0.30),
-        master: MasterParams::from_values(0.55, 0.35, 0.30, -1.0, 0.40),
-    }
-}
@@ -3,12 +3,194 @@ mod dsp;
 mod kits;
 mod params;
 
-use crate::dsp::fast_tanh;
-use drum_engine::{DrumSlot, N_SLOTS, SLOT_TYPES};
+use crate::dsp::{
+    Allpass, Biquad, DelayLine, FilterMode, ZdfSvf, fast_tanh_f64, flush_denormals,
+    flush_denormals_f64, lcg_bipolar, soft_knee, tpdf_dither, try_zeroed,
+};
+use drum_engine::{
+    DrumSlot, MAX_LAYER_SAMPLES, MAX_LAYER_SEC, MacroMods, N_SLOTS, SLOT_TYPES, max_voice_sec,
+};
+use kits::{FACTORY_KITS, KitSnapshot, N_USER_KITS};
 use nih_plug::prelude::*;
-use params::{DrumParams, DrumSlotParams, MasterParams};
+use params::{DrumParams, MasterParams};
+use std::collections::HashMap;
 use std::num::NonZeroU32;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// One 16-bit LSB, the scale of the optional output dither.
+const DITHER_LSB: f32 = 1.0 / 32768.0;
+
+/// Longest per-slot Haas delay, kept short for mono compatibility.
+const HAAS_MAX_MS: f32 = 12.0;
+/// Time constant of the Haas delay glide, so automation and pan crossing centre
+/// move the delay rather than jump it.
+const HAAS_GLIDE_SEC: f32 = 0.02;
+
+/// Foot controller CC used as a continuous hi-hat pedal.
+const HAT_PEDAL_CC: u8 = 4;
+
+/// Capacity of the scheduled-hit queue (flams etc.). Extra hits are dropped.
+const MAX_PENDING: usize = 16;
+
+/// Scale on the summed slots (-6 dB), so a full default kit hitting at once, plus
+/// its reverb sends, stays under 0 dBFS.
+const MIX_HEADROOM: f64 = 0.5;
+
+/// Sum-guard knee and ceiling: the drive expects roughly unit-level input.
+const SUM_KNEE: f64 = 1.0;
+const SUM_CEILING: f64 = 2.0;
+
+/// Internal sub-mix groups a slot can be routed to.
+pub(crate) const GROUP_NAMES: [&str; 2] = ["Drums", "Perc"];
+pub(crate) const N_GROUPS: usize = GROUP_NAMES.len();
+
+/// Mid-channel trim (-1.5 dB) for center compensation: half the +3 dB that
+/// equal-power panning adds to center-panned slots when summed to mono.
+const CENTER_TRIM: f64 = 0.841;
+
+/// Default compressor attack and release (also what the group buses use).
+pub(crate) const COMP_ATTACK_MS: f32 = 5.0;
+pub(crate) const COMP_RELEASE_MS: f32 = 80.0;
+
+/// Slot decay at and above which a decay-aware send passes at full level.
+const SEND_DECAY_REF_MS: f32 = 400.0;
+
+/// Tempo-synced LFO rates, indexed by the master `lfo_rate` param, and their
+/// periods in beats.
+pub(crate) const LFO_RATES: [&str; 7] = ["4 Bars", "2 Bars", "1 Bar", "1/2", "1/4", "1/8", "1/16"];
+const LFO_BEATS: [f64; 7] = [16.0, 8.0, 4.0, 2.0, 1.0, 0.5, 0.25];
+
+/// LFO destinations, indexed by the master `lfo_dest` param.
+pub(crate) const LFO_DESTINATIONS: [&str; 3] = ["Reverb", "Tone", "Pitch"];
+
+/// LFO swing at full depth: tone macro ±0.5, pitch ±12 st (reverb amount ±1).
+const LFO_TONE_SWING: f32 = 0.5;
+const LFO_PITCH_ST: f32 = 12.0;
+
+/// Tempo the LFO free-runs at when the host reports none.
+const LFO_FALLBACK_BPM: f64 = 120.0;
+
+/// Which note wins when several notes share a slot, indexed by the master
+/// `note_priority` param.
+pub(crate) const NOTE_PRIORITIES: [&str; 3] = ["Last", "Highest", "Loudest"];
+
+/// Output meter ballistics, indexed by the master `meter_mode` param.
+pub(crate) const METER_MODES: [&str; 4] = ["Peak", "PPM", "RMS", "VU"];
+
+/// Per meter mode: attack and release time constants (s, 0 = instant), and
+/// whether the meter averages power rather than level.
+const METER_BALLISTICS: [(f32, f32, bool); 4] = [
+    // Peak: instant rise, falls 20 dB in 1.7 s
+    (0.0, 0.738, false),
+    // PPM: ~10 ms integration, falls 24 dB in 2.8 s
+    (0.005, 1.013, false),
+    // RMS: 300 ms power average
+    (0.3, 0.3, true),
+    // VU: rectified average reaching 99% in 300 ms, both ways
+    (0.065, 0.065, false),
+];
+
+/// Latest a timing-humanized hit can land, at full slot humanize.
+const TIMING_HUMANIZE_MAX_MS: f32 = 10.0;
+
+/// Output level (about -120 dB) below which the kit counts as silent.
+const SILENCE: f32 = 1e-6;
+/// How long the output must stay silent before blocks are skipped; longer than
+/// the reverb's delay lines so nothing still in flight gets cut.
+const SILENCE_HOLD_SEC: f32 = 0.3;
+
+/// A hit waiting to fire `delay` samples from now.
+#[derive(Clone, Copy)]
+struct PendingHit {
+    slot: usize,
+    velocity: f32,
+    /// Per-note pitch offset in semitones (tom notes)
+    pitch: f32,
+    delay: u32,
+    /// Fire through `hit` (pedal, flam) rather than triggering the slot directly
+    full: bool,
+}
+
+/// Host transport as seen at the start of a block. Sync features read this rather
+/// than the host, so every sample of a block agrees even if the tempo moves mid-block.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TransportSnapshot {
+    pub playing: bool,
+    /// Beats per minute, if the host reports it
+    pub tempo: Option<f64>,
+    /// Song position in quarter notes at the block start, if reported
+    pub pos_beats: Option<f64>,
+    pub sample_rate: f32,
+}
+
+impl TransportSnapshot {
+    pub fn capture(transport: &Transport, sample_rate: f32) -> Self {
+        Self {
+            playing: transport.playing,
+            tempo: transport.tempo.filter(|t| *t > 0.0),
+            pos_beats: transport.pos_beats(),
+            sample_rate,
+        }
+    }
+
+    /// Length of one beat in samples, when the tempo is known.
+    pub fn samples_per_beat(&self) -> Option<f32> {
+        self.tempo.map(|bpm| (60.0 / bpm) as f32 * self.sample_rate)
+    }
+
+    /// Song position in beats `offset` samples into the block, when known.
+    pub fn beats_at(&self, offset: u32) -> Option<f64> {
+        let spb = self.samples_per_beat()? as f64;
+        Some(self.pos_beats? + offset as f64 / spb)
+    }
+}
+
+/// Lock-free counters for events the plugin dropped, for diagnosing integration issues.
+#[derive(Default)]
+pub struct Diagnostics {
+    unmapped_notes: AtomicU32,
+    note_offs: AtomicU32,
+    other_events: AtomicU32,
+    dropped_hits: AtomicU32,
+    silent_blocks: AtomicU32,
+    reverb_alloc_failures: AtomicU32,
+}
+
+/// Point-in-time copy of [`Diagnostics`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DiagnosticsSnapshot {
+    /// NoteOns whose note maps to no slot
+    pub unmapped_notes: u32,
+    /// NoteOffs (ignored by the one-shot engines)
+    pub note_offs: u32,
+    /// Any other event type (CCs, pressure, ...)
+    pub other_events: u32,
+    /// Scheduled hits (flams) lost because the queue was full
+    pub dropped_hits: u32,
+    /// Blocks skipped because the kit was silent (not a drop, just saved work)
+    pub silent_blocks: u32,
+    /// Times the reverb couldn't allocate its buffer and was bypassed
+    pub reverb_alloc_failures: u32,
+}
+
+impl Diagnostics {
+    #[inline]
+    fn bump(counter: &AtomicU32) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> DiagnosticsSnapshot {
+        DiagnosticsSnapshot {
+            unmapped_notes: self.unmapped_notes.load(Ordering::Relaxed),
+            note_offs: self.note_offs.load(Ordering::Relaxed),
+            other_events: self.other_events.load(Ordering::Relaxed),
+            dropped_hits: self.dropped_hits.load(Ordering::Relaxed),
+            silent_blocks: self.silent_blocks.load(Ordering::Relaxed),
+            reverb_alloc_failures: self.reverb_alloc_failures.load(Ordering::Relaxed),
+        }
+    }
+}
 
 // Plugin struct
 
@@ -17,8 +199,68 @@ pub struct Drumini {
     sample_rate: f32,
     slots: [DrumSlot; N_SLOTS],
 
+    haas: [HaasDelay; N_SLOTS],
+    drive: MasterDrive,
+    group_comp: [SimpleComp; N_GROUPS],
     comp: SimpleComp,
     reverb: SimpleReverb,
+    bass_mono: BassMono,
+    air: AirShelf,
+    stereoize: Stereoize,
+    correlation: CorrelationMeter,
+    meter: LevelMeter,
+
+    dither_state: u32,
+    pending: [Option<PendingHit>; MAX_PENDING],
+
+    /// Hi-hat openness from the pedal CC (0 = closed, 1 = open); `None` until one arrives.
+    hat_openness: Option<f32>,
+
+    /// Velocity and note of the hit each slot last took, for note priority.
+    held_notes: [Option<(f32, u8)>; N_SLOTS],
+
+    /// Host transport state on the previous block, to detect stops.
+    was_playing: bool,
+
+    /// Transport captured at the top of the current block.
+    transport: TransportSnapshot,
+
+    /// Kit index last glided to, so the Kit param only acts when it changes. The kit
+    /// itself lives on in `DrumParams::kit_override`.
+    applied_kit: i32,
+    /// Put the kit override back over the host's values on the next block.
+    restore_kit: bool,
+    /// Hand the kit override's macros back to the host on the next block, set
+    /// when a user kit is recalled.
+    release_kit: AtomicBool,
+
+    /// Consecutive samples the output has stayed below `SILENCE`.
+    quiet_samples: u32,
+
+    /// Per-slot RNGs for trigger probability, reseeded from the slot seeds on reset.
+    chance_state: [u32; N_SLOTS],
+
+    /// Noise/humanize RNG handed to the slots, kept so `reset` doesn't drop it.
+    #[cfg(test)]
+    random_source: drum_engine::RandomSource,
+
+    /// LFO position in its cycle (0..1).
+    lfo_phase: f64,
+
+    /// RNG for timing humanize, separate so it doesn't shift the dither sequence.
+    timing_state: u32,
+    /// Host is bouncing rather than playing live.
+    offline: bool,
+
+    /// Set when an output sample exceeded ±1.0; cleared by `take_clipped`.
+    clipped: Arc<AtomicBool>,
+    /// Output correlation (f32 bits), published once per block.
+    correlation_out: Arc<AtomicU32>,
+    /// Output meter level (f32 bits, linear), published once per block.
+    meter_out: Arc<AtomicU32>,
+    /// Set while any slot sounds or the output (reverb tail included) is above `SILENCE`.
+    playing_any: Arc<AtomicBool>,
+    diagnostics: Arc<Diagnostics>,
 }
 
 impl Default for Drumini {
@@ -31,75 +273,633 @@ impl Default for Drumini {
             params,
             sample_rate: sr,
             slots,
+            haas: core::array::from_fn(|_| HaasDelay::new(sr)),
+            drive: MasterDrive::new(sr),
+            group_comp: core::array::from_fn(|_| SimpleComp::new(sr)),
             comp: SimpleComp::new(sr),
             reverb: SimpleReverb::new(sr),
+            bass_mono: BassMono::new(sr),
+            air: AirShelf::new(sr),
+            stereoize: Stereoize::new(sr),
+            correlation: CorrelationMeter::new(sr),
+            meter: LevelMeter::new(sr),
+            dither_state: 0x1234_5678,
+            pending: [None; MAX_PENDING],
+            hat_openness: None,
+            held_notes: [None; N_SLOTS],
+            was_playing: false,
+            transport: TransportSnapshot::default(),
+            applied_kit: 0,
+            restore_kit: false,
+            release_kit: AtomicBool::new(false),
+            quiet_samples: 0,
+            chance_state: core::array::from_fn(|i| chance_seed(1 + i as u32)),
+            #[cfg(test)]
+            random_source: lcg_bipolar,
+            lfo_phase: 0.0,
+            timing_state: 0x2545_f491,
+            offline: false,
+            clipped: Arc::new(AtomicBool::new(false)),
+            correlation_out: Arc::new(AtomicU32::new(0.0f32.to_bits())),
+            meter_out: Arc::new(AtomicU32::new(0.0f32.to_bits())),
+            playing_any: Arc::new(AtomicBool::new(false)),
+            diagnostics: Arc::new(Diagnostics::default()),
         }
     }
 }
 
-// Plugin impl
+impl Drumini {
+    /// Whether the output clipped since the last call. Reading clears the flag.
+    pub fn take_clipped(&self) -> bool {
+        self.clipped.swap(false, Ordering::Relaxed)
+    }
 
-impl Plugin for Drumini {
-    const NAME: &'static str = "Drumini";
-    const VENDOR: &'static str = "me";
-    const URL: &'static str = "https://github.com";
-    const EMAIL: &'static str = "me@later.com";
-    const VERSION: &'static str = "0.1.0";
+    /// Shared handle to the clip flag, for an editor to poll.
+    pub fn clip_flag(&self) -> Arc<AtomicBool> {
+        self.clipped.clone()
+    }
 
-    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
-        main_input_channels: None,
-        main_output_channels: NonZeroU32::new(2),
-        aux_input_ports: &[],
-        aux_output_ports: &[],
-        names: PortNames::const_default(),
-    }];
+    /// Whether anything was audible in the last block, so an editor can skip
+    /// redrawing meters while idle.
+    pub fn is_playing_any(&self) -> bool {
+        self.playing_any.load(Ordering::Relaxed)
+    }
 
-    const MIDI_INPUT: MidiConfig = MidiConfig::Basic;
-    const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
-    const SAMPLE_ACCURATE_AUTOMATION: bool = true;
+    /// Shared handle to the playing flag, for an editor to poll.
+    pub fn playing_flag(&self) -> Arc<AtomicBool> {
+        self.playing_any.clone()
+    }
 
-    type SysExMessage = ();
-    type BackgroundTask = ();
+    /// Stereo correlation of the output, -1 (out of phase) to 1 (mono); 0 when silent.
+    pub fn correlation(&self) -> f32 {
+        f32::from_bits(self.correlation_out.load(Ordering::Relaxed))
+    }
 
-    fn params(&self) -> Arc<dyn Params> {
-        self.params.clone()
+    /// Shared handle to the correlation meter (f32 bits), for an editor to poll.
+    pub fn correlation_meter(&self) -> Arc<AtomicU32> {
+        self.correlation_out.clone()
     }
 
-    fn initialize(
-        &mut self,
-        _io: &AudioIOLayout,
-        buffer_config: &BufferConfig,
-        _ctx: &mut impl InitContext<Self>,
-    ) -> bool {
-        self.sample_rate = buffer_config.sample_rate.max(1.0);
+    /// Output level (linear, louder channel) under the chosen meter ballistics.
+    pub fn output_level(&self) -> f32 {
+        f32::from_bits(self.meter_out.load(Ordering::Relaxed))
+    }
+
+    /// Shared handle to the output meter (f32 bits), for an editor to poll.
+    pub fn level_meter(&self) -> Arc<AtomicU32> {
+        self.meter_out.clone()
+    }
+
+    /// Transport as captured at the start of the last processed block.
+    pub fn transport(&self) -> TransportSnapshot {
+        self.transport
+    }
+
+    /// Shared handle to the dropped-event counters.
+    pub fn diagnostics(&self) -> Arc<Diagnostics> {
+        self.diagnostics.clone()
+    }
+
+    /// Every parameter's plain value keyed by its id. Allocates; keep off the audio thread.
+    pub fn param_snapshot(&self) -> HashMap<String, f32> {
+        self.params
+            .param_map()
+            .into_iter()
+            // SAFETY: the pointers come from `self.params`, which outlives this call
+            .map(|(id, ptr, _)| (id, unsafe { ptr.unmodulated_plain_value() }))
+            .collect()
+    }
+
+    /// Set every parameter named in `snapshot` to its plain value through the host,
+    /// as an editor would, so the host, the saved state and the sound all agree.
+    /// Unknown ids are ignored; returns how many values were set. Allocates; call
+    /// from the GUI thread.
+    pub fn apply_param_snapshot(
+        &self,
+        context: &dyn GuiContext,
+        snapshot: &HashMap<String, f32>,
+    ) -> usize {
+        self.set_params(context, |id| snapshot.get(id).copied())
+    }
+
+    /// Set each parameter `value` has a plain value for through the host, see
+    /// [`Self::apply_param_snapshot`].
+    fn set_params(&self, context: &dyn GuiContext, value: impl Fn(&str) -> Option<f32>) -> usize {
+        let mut applied = 0;
+        for (id, ptr, _) in self.params.param_map() {
+            let Some(value) = value(&id) else {
+                continue;
+            };
+            // SAFETY: as above, `self.params` owns the parameter
+            unsafe {
+                let normalized = ptr.preview_normalized(value);
+                context.raw_begin_set_parameter(ptr);
+                context.raw_set_parameter_normalized(ptr, normalized);
+                context.raw_end_set_parameter(ptr);
+            }
+            applied += 1;
+        }
+        applied
+    }
+
+    /// Every parameter but the Kit selector as heard: the host's values, with the
+    /// macros a factory kit still covers at the kit's values. Allocates and takes a
+    /// lock; keep off the audio thread.
+    fn heard_snapshot(&self) -> KitSnapshot {
+        let held = self
+            .params
+            .kit_override
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|ov| kits::covered(ov, &self.params))
+            .unwrap_or_default();
+        let kit = self.params.master.kit.as_ptr();
+        self.params
+            .param_map()
+            .into_iter()
+            .filter(|(_, ptr, _)| *ptr != kit)
+            .map(|(id, ptr, _)| {
+                let value = match held.iter().find(|(p, _)| *p == ptr) {
+                    Some(&(_, value)) => value,
+                    // SAFETY: the pointers come from `self.params`, which outlives this call
+                    None => unsafe { ptr.unmodulated_plain_value() },
+                };
+                (id, value)
+            })
+            .collect()
+    }
+
+    /// Store the sound as currently heard in user kit `n`. Returns false if `n` is
+    /// out of range. Allocates and takes locks; keep off the audio thread.
+    pub fn save_user_kit(&self, n: usize) -> bool {
+        if n >= N_USER_KITS {
+            return false;
+        }
+        let kit = self.heard_snapshot();
+        let mut kits = self
+            .params
+            .user_kits
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        kits[n] = Some(kit);
+        true
+    }
+
+    /// Set every parameter to user kit `n` through the host; the macros glide over
+    /// as with any host change. Returns false if the slot is out of range or empty.
+    /// Takes a lock; call from the GUI thread.
+    pub fn recall_user_kit(&self, context: &dyn GuiContext, n: usize) -> bool {
+        let kits = self
+            .params
+            .user_kits
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+        let Some(Some(kit)) = kits.get(n) else {
+            return false;
+        };
+        self.recall_snapshot(context, kit);
+        true
+    }
+
+    /// Set every parameter in `kit` through the host. The snapshot already holds
+    /// the factory kit's values, so the kit lets go of its macros.
+    fn recall_snapshot(&self, context: &dyn GuiContext, kit: &KitSnapshot) {
+        self.release_kit.store(true, Ordering::Relaxed);
+        self.set_params(context, |id| kit.get(id).copied());
+    }
+
+    /// A/B compare: remember the sound as heard as the active side. Allocates and
+    /// takes locks; keep off the audio thread.
+    pub fn ab_store(&self) {
+        let kit = self.heard_snapshot();
+        let mut ab = self
+            .params
+            .ab_compare
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        let side = ab.0;
+        ab.1[side] = Some(kit);
+    }
+
+    /// A/B compare: overwrite B with A.
+    pub fn ab_copy_a_to_b(&self) {
+        let mut ab = self
+            .params
+            .ab_compare
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        ab.1[1] = ab.1[0].clone();
+    }
+
+    /// A/B compare: switch to `side` (0 = A, 1 = B), setting its values through the
+    /// host. Edits made on the side being left are kept; an empty side starts as a
+    /// copy. Returns false for a bad side. Takes locks; call from the GUI thread.
+    pub fn ab_recall(&self, context: &dyn GuiContext, side: usize) -> bool {
+        if side > 1 {
+            return false;
+        }
+        let kit = self.heard_snapshot();
+        let mut ab = self
+            .params
+            .ab_compare
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        let active = ab.0;
+        ab.1[active] = Some(kit);
+        ab.0 = side;
+        match &ab.1[side] {
+            Some(kit) => self.recall_snapshot(context, kit),
+            None => ab.1[side] = ab.1[active].clone(),
+        }
+        true
+    }
+
+    /// A/B compare: flip to the other side.
+    pub fn ab_swap(&self, context: &dyn GuiContext) {
+        let active = self
+            .params
+            .ab_compare
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .0;
+        self.ab_recall(context, 1 - active.min(1));
+    }
+
+    /// Layer a mono one-shot under slot `slot`, played from the start on every hit
+    /// and resampled from `sample_rate` on the fly. Truncated to `MAX_LAYER_SEC` and
+    /// `MAX_LAYER_SAMPLES`, so a high source rate can't blow up the saved state.
+    /// Returns false for a bad slot or rate. Allocates; keep off the audio thread.
+    pub fn load_layer(&self, slot: usize, pcm: &[f32], sample_rate: f32) -> bool {
+        if slot >= N_SLOTS || !sample_rate.is_finite() || sample_rate <= 0.0 {
+            return false;
+        }
+        let len = pcm
+            .len()
+            .min((MAX_LAYER_SEC * sample_rate) as usize)
+            .min(MAX_LAYER_SAMPLES);
+        let layer = (sample_rate, pcm[..len].to_vec());
+        let mut layers = self
+            .params
+            .slot_layers
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        layers[slot] = Some(layer);
+        true
+    }
+
+    /// Drive every slot's noise and humanize from `rng` instead of the built-in LCG,
+    /// so tests can pin exact per-hit values. Test builds only.
+    #[cfg(test)]
+    pub fn set_random_source(&mut self, rng: drum_engine::RandomSource) {
+        self.random_source = rng;
         for slot in &mut self.slots {
-            slot.set_sample_rate(self.sample_rate);
+            slot.set_random_source(rng);
         }
-        self.comp.set_sample_rate(self.sample_rate);
-        self.reverb.set_sample_rate(self.sample_rate);
+    }
+
+    /// Samples to keep rendering after the last note so everything dies away: the
+    /// longest hit any slot's decay range allows (or a full sample layer), plus any
+    /// flam or timing delay, plus the reverb's early reflections and its current
+    /// tail down to `SILENCE`.
+    pub fn tail_samples(&self) -> u32 {
+        let p = self.params.as_ref();
+        let voice_sec = (0..N_SLOTS)
+            .map(|i| {
+                let slot = p.slot(i);
+                // Decay link stretches hits tuned down: allow for the lowest tom note
+                // and full pitch humanize below the current tuning
+                let lowest = slot.pitch.value() + p.master.kit_pitch.value() - 5.0 - 3.0;
+                let stretch = 2.0f32.powf(-slot.decay_link.value() * lowest.min(0.0) / 12.0);
+                max_voice_sec(slot.decay.preview_plain(1.0)) * stretch
+            })
+            .fold(MAX_LAYER_SEC, f32::max);
+        // Longest flam (40 ms) on top of the latest timing-humanized hit
+        let delay_sec = 0.04 + TIMING_HUMANIZE_MAX_MS * 0.001;
+
+        let reverb_sec = if p.master.reverb.value() > 0.001 {
+            // RT60 reaches -60 dB; SILENCE sits another 60 dB down
+            REVERB_ER_SEC + 2.0 * p.master.reverb_decay.value()
+        } else {
+            0.0
+        };
+
+        ((voice_sec + delay_sec + reverb_sec) * self.sample_rate).ceil() as u32
+    }
+
+    /// Remove slot `slot`'s sample layer.
+    pub fn clear_layer(&self, slot: usize) {
+        let mut layers = self
+            .params
+            .slot_layers
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(layer) = layers.get_mut(slot) {
+            *layer = None;
+        }
+    }
+
+    /// Stereo impulse response of the reverb at the current room settings, `len`
+    /// samples long, with the wet path at full amount. Renders on a fresh reverb, so
+    /// the running tail is untouched. Allocates; keep off the audio thread.
+    pub fn reverb_impulse_response(&self, len: usize) -> (Vec<f32>, Vec<f32>) {
+        let mut reverb = SimpleReverb::new(self.sample_rate);
+        reverb.configure(&self.params.master);
+
+        let mut ir_l = Vec::with_capacity(len);
+        let mut ir_r = Vec::with_capacity(len);
+        for i in 0..len {
+            let x = if i == 0 { 1.0 } else { 0.0 };
+            let (l, r) = reverb.process(0.0, 0.0, x, x, 1.0);
+            ir_l.push(l as f32);
+            ir_r.push(r as f32);
+        }
+        (ir_l, ir_r)
+    }
+
+    /// Hit slot `slot_idx` without a MIDI event. The hit is queued and fires on
+    /// the first sample of the next `process` call, through the same path as a
+    /// played note (pedal, flam). Doesn't allocate; returns false for a bad slot.
+    pub fn trigger_slot(&mut self, slot_idx: usize, velocity: f32) -> bool {
+        if slot_idx >= N_SLOTS {
+            return false;
+        }
+        self.schedule(PendingHit {
+            slot: slot_idx,
+            velocity: velocity.clamp(0.0, 1.0),
+            pitch: 0.0,
+            delay: 0,
+            full: true,
+        });
         true
     }
 
-    fn reset(&mut self) {
-        for (i, slot) in self.slots.iter_mut().enumerate() {
-            *slot = DrumSlot::new(SLOT_TYPES[i], self.sample_rate);
+    /// Start the LFO on a block: lock it to the song position while the host plays
+    /// (it free-runs otherwise) and return its phase step per sample.
+    fn lfo_start(&mut self, rate: usize) -> f64 {
+        let beats = LFO_BEATS[rate.min(LFO_BEATS.len() - 1)];
+        if let (true, Some(pos)) = (self.transport.playing, self.transport.pos_beats) {
+            self.lfo_phase = (pos / beats).rem_euclid(1.0);
         }
-        self.comp.reset();
-        self.reverb.reset();
+        let bpm = self.transport.tempo.unwrap_or(LFO_FALLBACK_BPM);
+        1.0 / (beats * 60.0 / bpm * self.sample_rate as f64)
     }
 
-    fn process(
+    /// LFO value (-1..1) at the current sample, then step past it.
+    #[inline]
+    fn lfo_next(&mut self, step: f64) -> f32 {
+        let value = (self.lfo_phase * std::f64::consts::TAU).sin() as f32;
+        self.lfo_phase = (self.lfo_phase + step).fract();
+        value
+    }
+
+    /// Note priority against the hit the slot is still playing. Records the note
+    /// as the slot's current one when it gets through.
+    fn note_takes_slot(&mut self, slot_idx: usize, vel: f32, note: u8, p: &DrumParams) -> bool {
+        let slot = &self.slots[slot_idx];
+        if let (Some((held_vel, held_note)), true) = (self.held_notes[slot_idx], slot.is_active()) {
+            let blocked = match p.master.note_priority.value() {
+                // Highest: a lower note can't cut a higher one short
+                1 => note < held_note,
+                // Loudest: a note quieter than what's still ringing is dropped
+                2 => vel < held_vel * slot.envelope(),
+                // Last: every note retriggers
+                _ => false,
+            };
+            if blocked {
+                return false;
+            }
+        }
+        self.held_notes[slot_idx] = Some((vel, note));
+        true
+    }
+
+    /// A played note: either hit now or, with timing humanize, a little late.
+    fn note_on(&mut self, slot_idx: usize, vel: f32, pitch: f32, p: &DrumParams) {
+        let timing = p.master.humanize_timing.value().clamp(0.0, 1.0);
+        let gated = self.offline && p.master.timing_live_only.value();
+        if timing > 0.0 && !gated {
+            let h = p
+                .slot(slot_idx)
+                .humanize
+                .smoothed
+                .previous_value()
+                .clamp(0.0, 1.0);
+            let r = (lcg_bipolar(&mut self.timing_state) + 1.0) * 0.5;
+            let delay_ms = r * timing * h * TIMING_HUMANIZE_MAX_MS;
+            let delay = (delay_ms * 0.001 * self.sample_rate) as u32;
+            if delay > 0 {
+                self.schedule(PendingHit {
+                    slot: slot_idx,
+                    velocity: vel,
+                    pitch,
+                    delay,
+                    full: true,
+                });
+                return;
+            }
+        }
+        self.hit(slot_idx, vel, pitch, p);
+    }
+
+    /// Trigger a slot now and queue any follow-up hits it asks for.
+    fn hit(&mut self, slot_idx: usize, vel: f32, pitch: f32, p: &DrumParams) {
+        let slot_idx = self.strike(slot_idx, vel, pitch, p);
+
+        // Flam: a later, quieter copy. Queued hits never flam themselves.
+        let flam = p.slot(slot_idx).flam.value().clamp(0.0, 1.0);
+        if flam > 0.0 {
+            let delay_ms = 8.0 + 32.0 * flam;
+            self.schedule(PendingHit {
+                slot: slot_idx,
+                velocity: vel * (0.75 - 0.35 * flam),
+                pitch,
+                delay: (delay_ms * 0.001 * self.sample_rate) as u32,
+                full: false,
+            });
+        }
+    }
+
+    /// Trigger the voice behind `slot_idx` and return the slot that played.
+    fn strike(&mut self, slot_idx: usize, vel: f32, pitch: f32, p: &DrumParams) -> usize {
+        // With the pedal in use, both hat notes play the closed-hat voice with a
+        // decay between the closed and open settings
+        if let (Some(open), HAT_CLOSED | HAT_OPEN) = (self.hat_openness, slot_idx) {
+            let closed_ms = p.hat_closed.decay.smoothed.previous_value();
+            let open_ms = p.hat_open.decay.smoothed.previous_value();
+            let decay_ms = closed_ms * (open_ms / closed_ms).powf(open);
+            self.slots[HAT_CLOSED].trigger_with_decay(
+                vel,
+                pitch,
+                &p.hat_closed,
+                &p.master,
+                decay_ms,
+            );
+            return HAT_CLOSED;
+        }
+
+        self.slots[slot_idx].trigger(vel, pitch, p.slot(slot_idx), &p.master);
+        slot_idx
+    }
+
+    fn schedule(&mut self, hit: PendingHit) {
+        match self.pending.iter_mut().find(|h| h.is_none()) {
+            Some(free) => *free = Some(hit),
+            None => Diagnostics::bump(&self.diagnostics.dropped_hits),
+        }
+    }
+
+    /// Count down queued hits and fire the ones that are due.
+    fn tick_pending(&mut self, p: &DrumParams) {
+        for i in 0..MAX_PENDING {
+            let Some(hit) = &mut self.pending[i] else {
+                continue;
+            };
+            if hit.delay > 0 {
+                hit.delay -= 1;
+                continue;
+            }
+
+            let hit = *hit;
+            self.pending[i] = None;
+            if hit.full {
+                self.hit(hit.slot, hit.velocity, hit.pitch, p);
+            } else {
+                self.strike(hit.slot, hit.velocity, hit.pitch, p);
+            }
+        }
+    }
+
+    /// Render `samples` frames into `outputs` (left, right, then silence), pulling
+    /// this block's events from `next_event` in timing order. With no output
+    /// channels the block still runs, so events, voices and smoothers stay in step
+    /// with the host; only the writes are skipped.
+    fn render(
         &mut self,
-        buffer: &mut Buffer<'_>,
-        _aux: &mut AuxiliaryBuffers<'_>,
-        ctx: &mut impl ProcessContext<Self>,
+        outputs: &mut [&mut [f32]],
+        samples: usize,
+        transport: TransportSnapshot,
+        mut next_event: impl FnMut() -> Option<PluginNoteEvent<Self>>,
     ) -> ProcessStatus {
+        // An empty block has no frame for its events to land on: drop them
+        if samples == 0 {
+            while next_event().is_some() {}
+            return ProcessStatus::Normal;
+        }
+
         let params = self.params.clone();
-        let mut next_event = ctx.next_event();
+        let mut event = next_event();
+        let mut block_peak = 0.0f32;
+
+        self.transport = transport;
+
+        // Tail on stop: choke the reverb feedback once the host stops
+        let playing = self.transport.playing;
+        if playing != self.was_playing {
+            self.was_playing = playing;
+            self.reverb
+                .set_choked(!playing && params.master.cut_tail_on_stop.value());
+        }
+
+        self.meter
+            .set_mode(params.master.meter_mode.value() as usize);
+
+        let lfo_depth = params.master.lfo_depth.value();
+        let lfo_dest = params.master.lfo_dest.value();
+        let lfo_step = self.lfo_start(params.master.lfo_rate.value() as usize);
+
+        // Kit changes land on a block boundary and glide in through the smoothers. The
+        // override is only touched when the lock is free; a busy block checks next time.
+        let kit = params.master.kit.value();
+        if let Ok(mut ov) = params.kit_override.try_write() {
+            let release = self.release_kit.swap(false, Ordering::Relaxed);
+            // Right after a reset, an override for another kit means the Kit param
+            // moved while the plugin was inactive
+            let moved = self.restore_kit && ov.as_ref().is_some_and(|ov| ov.0 != kit);
+            if kit != self.applied_kit || moved {
+                self.applied_kit = kit;
+                *ov = FACTORY_KITS
+                    .get(kit as usize)
+                    .map(|k| k.apply(kit, &params, self.sample_rate));
+            } else if let Some(ov) = ov.as_mut() {
+                if release {
+                    kits::release_all(ov, &params, self.sample_rate);
+                } else if self.restore_kit {
+                    kits::reapply(ov, &params);
+                } else {
+                    kits::release_moved(ov, &params);
+                }
+            }
+            self.restore_kit = false;
+        }
+
+        // Silent kit and nothing to play: write zeros and skip the per-sample work
+        let idle =
+            self.slots.iter().all(|s| !s.is_active()) && self.pending.iter().all(Option::is_none);
+        let hold = (SILENCE_HOLD_SEC * self.sample_rate) as u32;
+        if idle && event.is_none() && self.quiet_samples >= hold {
+            // Dither carries on through skipped blocks, so the noise floor doesn't gate
+            let dither = params.master.dither.value();
+            for (ch, channel) in outputs.iter_mut().enumerate() {
+                let channel = &mut channel[..samples];
+                if dither && ch < 2 {
+                    for x in channel.iter_mut() {
+                        *x = tpdf_dither(&mut self.dither_state, DITHER_LSB);
+                    }
+                } else {
+                    channel.fill(0.0);
+                }
+            }
+            // Smoothers, the LFO and the meter keep moving through skipped blocks, as if
+            // rendered
+            params.skip_smoothers(samples as u32);
+            if lfo_depth > 0.0 {
+                self.lfo_phase = (self.lfo_phase + lfo_step * samples as f64).fract();
+            }
+            self.meter.skip(samples);
+            self.meter_out
+                .store(self.meter.value().to_bits(), Ordering::Relaxed);
+            Diagnostics::bump(&self.diagnostics.silent_blocks);
+            self.playing_any.store(false, Ordering::Relaxed);
+            self.correlation = CorrelationMeter::new(self.sample_rate);
+            self.correlation_out
+                .store(0.0f32.to_bits(), Ordering::Relaxed);
+            return ProcessStatus::Normal;
+        }
+
+        // Sample layers are swapped off the audio thread; if a load holds the lock,
+        // this block plays without them rather than waiting
+        let layers = params.slot_layers.try_read().ok();
+
+        for sample_idx in 0..samples {
+            // Master macros read only at trigger time still glide sample by sample
+            params.master.kit_pitch.smoothed.next();
+            params.master.velocity_curve.smoothed.next();
+
+            // Global macros: tightness and the LFO, both moving per sample
+            let mut mods = MacroMods::from_tightness(params.master.tightness.smoothed.next());
+            let mut lfo_reverb = 0.0;
+            if lfo_depth > 0.0 {
+                let lfo = self.lfo_next(lfo_step) * lfo_depth;
+                match lfo_dest {
+                    1 => mods.tone_offset = lfo * LFO_TONE_SWING,
+                    2 => mods.pitch_offset = lfo * LFO_PITCH_ST,
+                    _ => lfo_reverb = lfo,
+                }
+            }
+            for slot in &mut self.slots {
+                slot.mods = mods;
+            }
+
+            self.tick_pending(params.as_ref());
 
-        for (sample_idx, mut frame) in buffer.iter_samples().enumerate() {
-            // Sample-accurate events
-            while let Some(ev) = next_event {
+            // Sample-accurate events. Notes on the same frame don't depend on host
+            // order: they fire after that frame's CCs (so a pedal move lands first),
+            // in slot order, and two notes for one slot fire once: the higher note under
+            // "Highest" priority, the higher velocity otherwise.
+            let highest = params.master.note_priority.value() == 1;
+            let mut frame_hits = [None::<(f32, u8)>; N_SLOTS];
+            while let Some(ev) = event {
                 if ev.timing() != sample_idx as u32 {
                     break;
                 }
@@ -108,84 +908,463 @@ impl Plugin for Drumini {
                     NoteEvent::NoteOn { note, velocity, .. } => {
                         if let Some(slot_idx) = note_to_slot(note) {
                             let vel = velocity.clamp(0.0, 1.0);
-                            let p = params.as_ref();
-                            let slot_params = match_slot_params(slot_idx, p);
-                            let master = &p.master;
-                            self.slots[slot_idx].trigger(vel, slot_params, master);
+                            let hit = &mut frame_hits[slot_idx];
+                            let wins = |(v, n): (f32, u8)| if highest { note > n } else { vel > v };
+                            if hit.is_none_or(wins) {
+                                *hit = Some((vel, note));
+                            }
+                        } else {
+                            Diagnostics::bump(&self.diagnostics.unmapped_notes);
                         }
                     }
+                    NoteEvent::MidiCC {
+                        cc: HAT_PEDAL_CC,
+                        value,
+                        ..
+                    } => {
+                        // Pedal down (high values) closes the hat
+                        self.hat_openness = Some(1.0 - value.clamp(0.0, 1.0));
+                    }
                     NoteEvent::NoteOff { .. } => {
                         // One-shot drums, ignore for now
+                        Diagnostics::bump(&self.diagnostics.note_offs);
                     }
-                    _ => {}
+                    _ => Diagnostics::bump(&self.diagnostics.other_events),
                 }
 
-                next_event = ctx.next_event();
+                event = next_event();
+            }
+
+            for (slot_idx, hit) in frame_hits.into_iter().enumerate() {
+                let Some((vel, note)) = hit else {
+                    continue;
+                };
+                // Probability: a fixed loop can skip hits, reproducibly from the slot seed
+                let chance = params.slot(slot_idx).probability.value();
+                if chance < 1.0 {
+                    let r = (lcg_bipolar(&mut self.chance_state[slot_idx]) + 1.0) * 0.5;
+                    if r >= chance {
+                        continue;
+                    }
+                }
+                if self.note_takes_slot(slot_idx, vel, note, params.as_ref()) {
+                    self.note_on(slot_idx, vel, note_pitch(note), params.as_ref());
+                }
             }
 
             // Render and mix slots
-            let mut l = 0.0f32;
-            let mut r = 0.0f32;
+            let mut l: f32;
+            let mut r: f32;
 
             {
                 let p = params.as_ref();
                 let master = &p.master;
 
+                // The master chain runs in f64 from the slot sums through the reverb,
+                // so eight contributions and the feedback paths don't pile up rounding
+                // error; it drops to f32 for the output stages
+                let mut group_l = [0.0f64; N_GROUPS];
+                let mut group_r = [0.0f64; N_GROUPS];
+                let mut mix_send_l = 0.0f64;
+                let mut mix_send_r = 0.0f64;
+                // Slots kept out of the master comp: their own half of each group bus
+                let mut post_l = [0.0f64; N_GROUPS];
+                let mut post_r = [0.0f64; N_GROUPS];
+
                 for (i, slot) in self.slots.iter_mut().enumerate() {
-                    let slot_params = match_slot_params(i, p);
-                    let y = slot.process(slot_params, master);
+                    let slot_params = p.slot(i);
+                    let layer = layers
+                        .as_ref()
+                        .and_then(|layers| layers[i].as_ref())
+                        .map(|(rate, pcm)| (*rate, pcm.as_slice()));
+                    let (y_left, y_right) = slot.process(slot_params, master, layer);
+
+                    let pan = slot_params.pan.smoothed.next().clamp(-1.0, 1.0);
+                    let level = slot_params.level.smoothed.next();
 
-                    let pan = slot_params.pan.value().clamp(-1.0, 1.0);
-                    let level = slot_params.level.value();
+                    // Haas: delay the channel facing away from the pan position
+                    let haas = slot_params.haas.value().clamp(0.0, 1.0);
+                    let delay = haas * HAAS_MAX_MS * 0.001 * self.sample_rate;
+                    let (delay_l, delay_r) = if pan >= 0.0 {
+                        (delay, 0.0)
+                    } else {
+                        (0.0, delay)
+                    };
+                    let (y_l, y_r) = self.haas[i].process(y_left, y_right, delay_l, delay_r);
+
+                    // A slot's pan law overrides the master's unless left on "Master"
+                    let law = match slot_params.pan_law.value() {
+                        0 => master.pan_law.value(),
+                        v => v - 1,
+                    };
+                    let (gain_l, gain_r) = pan_to_gains(pan, law.max(0) as usize);
+                    let g = (slot_params.group.value() as usize).min(N_GROUPS - 1);
+                    let (bus_l, bus_r) = if slot_params.comp_bypass.value() {
+                        (&mut post_l, &mut post_r)
+                    } else {
+                        (&mut group_l, &mut group_r)
+                    };
+                    bus_l[g] += y_l as f64 * (level * gain_l) as f64;
+                    bus_r[g] += y_r as f64 * (level * gain_r) as f64;
+
+                    // Reverb send, optionally post-fader so muting a slot fades its tail input
+                    let mut send = slot_params.reverb_send.value().clamp(0.0, 1.0);
+                    if master.send_follows_level.value() {
+                        send *= level;
+                    }
+                    // Decay-aware send: short hits get a brief splash, long ones the full send
+                    if master.send_follows_decay.value() {
+                        let decay = slot_params.decay.smoothed.previous_value();
+                        send *= (decay / SEND_DECAY_REF_MS).sqrt().min(1.0);
+                    }
+                    // Gated send: only the attack reaches the reverb, not the tail
+                    if slot_params.gated_send.value() {
+                        send *= slot.send_gate();
+                    }
+                    // The group fader sets the send too, so a quieter group isn't wetter
+                    let group_gain = p.groups[g].gain.smoothed.previous_value();
+                    mix_send_l += y_l as f64 * (send * gain_l * group_gain) as f64;
+                    mix_send_r += y_r as f64 * (send * gain_r * group_gain) as f64;
+                }
+
+                // Group buses: gain and optional compression, then into the master. The
+                // group comp hears the whole group and its gain moves both halves alike
+                let mut mix_l = 0.0f64;
+                let mut mix_r = 0.0f64;
+                let mut post_mix_l = 0.0f64;
+                let mut post_mix_r = 0.0f64;
+                for (g, group) in p.groups.iter().enumerate() {
+                    let gain = group.gain.smoothed.next() as f64;
+                    let (gl, gr) = self.group_comp[g].gains(
+                        (group_l[g] + post_l[g]) * gain,
+                        (group_r[g] + post_r[g]) * gain,
+                        group.comp.value(),
+                        false,
+                        1.0,
+                    );
+                    mix_l += group_l[g] * gain * gl;
+                    mix_r += group_r[g] * gain * gr;
+                    post_mix_l += post_l[g] * gain * gl;
+                    post_mix_r += post_r[g] * gain * gr;
+                }
+                mix_l *= MIX_HEADROOM;
+                mix_r *= MIX_HEADROOM;
+                post_mix_l *= MIX_HEADROOM;
+                post_mix_r *= MIX_HEADROOM;
+                let send_l = mix_send_l * MIX_HEADROOM;
+                let send_r = mix_send_r * MIX_HEADROOM;
 
-                    let (gain_l, gain_r) = pan_to_gains(pan);
-                    l += y * level * gain_l;
-                    r += y * level * gain_r;
+                // Sum guard: transparent up to SUM_KNEE, never past SUM_CEILING. It shapes
+                // the whole sum and shares that gain with the slots outside the comp
+                if master.sum_guard.value() {
+                    let knee = |x: f64| {
+                        if x == 0.0 {
+                            1.0
+                        } else {
+                            soft_knee(x, SUM_KNEE, SUM_CEILING) / x
+                        }
+                    };
+                    let gl = knee(mix_l + post_mix_l);
+                    let gr = knee(mix_r + post_mix_r);
+                    mix_l *= gl;
+                    mix_r *= gr;
+                    post_mix_l *= gl;
+                    post_mix_r *= gr;
                 }
 
-                // Master drive (saturation)
-                let drive = master.drive.value().clamp(0.0, 1.0);
-                if drive > 0.0 {
-                    let drive_gain = 1.0 + drive * 4.0;
-                    let makeup = 1.0 / (1.0 + drive * 2.0);
-                    l = fast_tanh(l * drive_gain) * makeup;
-                    r = fast_tanh(r * drive_gain) * makeup;
+                // Center compensation: pull the mid back against side so a kit piled up
+                // in the middle keeps the same balance as a spread one
+                if master.center_comp.value() {
+                    let center = |l: f64, r: f64| {
+                        let mid = (l + r) * 0.5 * CENTER_TRIM;
+                        let side = (l - r) * 0.5;
+                        (mid + side, mid - side)
+                    };
+                    (mix_l, mix_r) = center(mix_l, mix_r);
+                    (post_mix_l, post_mix_r) = center(post_mix_l, post_mix_r);
                 }
 
-                // Master compressor
-                let comp_amt = master.comp.value().clamp(0.0, 1.0);
-                let (cl, cr) = self.comp.process(l, r, comp_amt);
-                l = cl;
-                r = cr;
+                // Master compressor; its detector listens to exactly this input
+                let sidechain = (mix_l, mix_r);
+                let comp_amt = master.comp.smoothed.next().clamp(0.0, 1.0);
+                self.comp.set_linked(!master.comp_dual_mono.value());
+                self.comp
+                    .set_times(master.comp_attack.value(), master.comp_release.value());
+                let (cl, cr) = self.comp.process(
+                    mix_l,
+                    mix_r,
+                    comp_amt,
+                    master.comp_auto_release.value(),
+                    master.comp_mix.value(),
+                );
+                // Slots kept out of the comp rejoin here, ahead of the drive
+                mix_l = cl + post_mix_l;
+                mix_r = cr + post_mix_r;
+
+                // Eco: the cheapest paths whatever the individual settings ask for
+                let eco = master.eco.value();
+
+                // Master drive (saturation); Eco keeps to the plain tanh
+                let drive = master.drive.smoothed.next().clamp(0.0, 1.0);
+                let model = if eco {
+                    0
+                } else {
+                    master.drive_model.value() as usize
+                };
+                (mix_l, mix_r) = self.drive.process(mix_l, mix_r, drive, model);
 
                 // Simple room-ish reverb
-                let rev_amt = master.reverb.value().clamp(0.0, 1.0);
-                let (rl, rr) = self.reverb.process(l, r, rev_amt);
-                l = rl;
-                r = rr;
-            }
+                let rev_amt = (master.reverb.smoothed.next() + lfo_reverb).clamp(0.0, 1.0);
+                self.reverb.configure(master);
+                let (rl, rr) = self.reverb.process(mix_l, mix_r, send_l, send_r, rev_amt);
+                l = rl as f32;
+                r = rr as f32;
+
+                let width = if eco { 0.0 } else { master.stereoize.value() };
+                let (wl, wr) = self.stereoize.process(l, r, width);
+                l = wl;
+                r = wr;
+
+                // Mono below the crossover so subs stay centred, reverb stays wide above
+                let (ml, mr) = self.bass_mono.process(l, r, master.bass_mono.value());
+                l = ml;
+                r = mr;
+
+                let (al, ar) = self.air.process(l, r, master.air.value());
+                l = al;
+                r = ar;
+
+                // SC Listen: monitor what the compressor hears instead of the mix
+                if master.sc_listen.value() {
+                    l = sidechain.0 as f32;
+                    r = sidechain.1 as f32;
+                }
+
+                if l.abs().max(r.abs()) > SILENCE {
+                    self.quiet_samples = 0;
+                } else {
+                    self.quiet_samples = self.quiet_samples.saturating_add(1);
+                }
+
+                // Polarity flip for layering against other drum sources
+                if master.invert_phase.value() {
+                    l = -l;
+                    r = -r;
+                }
+
+                // Balance: turn the far side down and leave the near one at unity, so
+                // it can never lift the output back over the ceiling
+                let balance = master.balance.value().clamp(-1.0, 1.0);
+                l *= 1.0 - balance.max(0.0);
+                r *= 1.0 + balance.min(0.0);
+
+                // Mono monitor: fold to mono (averaged, so a centred signal keeps its level)
+                if master.mono_monitor.value() {
+                    let m = (l + r) * 0.5;
+                    l = m;
+                    r = m;
+                }
 
-            let mut channels = frame.iter_mut();
-            if let Some(out_l) = channels.next() {
-                *out_l = l;
+                // Output dither (independent noise per channel), last so nothing after
+                // it can scale or fold the noise
+                if master.dither.value() {
+                    l += tpdf_dither(&mut self.dither_state, DITHER_LSB);
+                    r += tpdf_dither(&mut self.dither_state, DITHER_LSB);
+                }
             }
-            if let Some(out_r) = channels.next() {
-                *out_r = r;
+
+            block_peak = block_peak.max(l.abs()).max(r.abs());
+            self.correlation.process(l, r);
+            self.meter.process(l, r);
+
+            // Anything past stereo is left silent rather than holding host garbage
+            for (ch, out) in outputs.iter_mut().enumerate() {
+                out[sample_idx] = match ch {
+                    0 => l,
+                    1 => r,
+                    _ => 0.0,
+                };
             }
         }
 
+        if block_peak > 1.0 {
+            self.clipped.store(true, Ordering::Relaxed);
+        }
+        let sounding = block_peak > SILENCE || self.slots.iter().any(DrumSlot::is_active);
+        self.playing_any.store(sounding, Ordering::Relaxed);
+        self.correlation_out
+            .store(self.correlation.value().to_bits(), Ordering::Relaxed);
+        self.meter_out
+            .store(self.meter.value().to_bits(), Ordering::Relaxed);
+
         ProcessStatus::Normal
     }
 }
 
-// Helpers
+// Plugin impl
 
-fn pan_to_gains(pan: f32) -> (f32, f32) {
-    // Simple equal-power panning
-    let x = (pan + 1.0) * 0.5; // 0..1
-    let theta = x * std::f32::consts::FRAC_PI_2;
-    (theta.cos(), theta.sin())
-}
+impl Plugin for Drumini {
+    const NAME: &'static str = "Drumini";
+    const VENDOR: &'static str = "me";
+    const URL: &'static str = "https://github.com";
+    const EMAIL: &'static str = "me@later.com";
+    const VERSION: &'static str = "0.1.0";
+
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
+        main_input_channels: None,
+        main_output_channels: NonZeroU32::new(2),
+        aux_input_ports: &[],
+        aux_output_ports: &[],
+        names: PortNames::const_default(),
+    }];
+
+    const MIDI_INPUT: MidiConfig = MidiConfig::MidiCCs;
+    const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
+    const SAMPLE_ACCURATE_AUTOMATION: bool = true;
+
+    type SysExMessage = ();
+    type BackgroundTask = ();
+
+    fn params(&self) -> Arc<dyn Params> {
+        self.params.clone()
+    }
+
+    fn initialize(
+        &mut self,
+        _io: &AudioIOLayout,
+        buffer_config: &BufferConfig,
+        _ctx: &mut impl InitContext<Self>,
+    ) -> bool {
+        self.sample_rate = buffer_config.sample_rate.max(1.0);
+        self.offline = buffer_config.process_mode == ProcessMode::Offline;
+        for slot in &mut self.slots {
+            slot.set_sample_rate(self.sample_rate);
+        }
+        self.haas = core::array::from_fn(|_| HaasDelay::new(self.sample_rate));
+        self.comp.set_sample_rate(self.sample_rate);
+        for comp in &mut self.group_comp {
+            comp.set_sample_rate(self.sample_rate);
+        }
+        if !self.reverb.set_sample_rate(self.sample_rate) {
+            // Carry on without the room rather than failing the whole plugin
+            Diagnostics::bump(&self.diagnostics.reverb_alloc_failures);
+        }
+        self.bass_mono = BassMono::new(self.sample_rate);
+        self.air = AirShelf::new(self.sample_rate);
+        self.stereoize = Stereoize::new(self.sample_rate);
+        self.correlation = CorrelationMeter::new(self.sample_rate);
+        self.meter = LevelMeter::new(self.sample_rate);
+        self.drive = MasterDrive::new(self.sample_rate);
+        true
+    }
+
+    fn reset(&mut self) {
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            *slot = DrumSlot::new(SLOT_TYPES[i], self.sample_rate);
+            let seed = self.params.slot(i).seed.value() as u32;
+            slot.seed(seed);
+            #[cfg(test)]
+            slot.set_random_source(self.random_source);
+            self.chance_state[i] = chance_seed(seed);
+        }
+        for haas in &mut self.haas {
+            haas.reset();
+        }
+        self.comp.reset();
+        for comp in &mut self.group_comp {
+            comp.reset();
+        }
+        self.reverb.reset();
+        self.bass_mono = BassMono::new(self.sample_rate);
+        self.air = AirShelf::new(self.sample_rate);
+        self.stereoize.reset();
+        self.correlation = CorrelationMeter::new(self.sample_rate);
+        self.meter = LevelMeter::new(self.sample_rate);
+        self.drive = MasterDrive::new(self.sample_rate);
+        self.pending = [None; MAX_PENDING];
+        self.hat_openness = None;
+        self.quiet_samples = 0;
+
+        // The wrapper has just reset every smoother to the host's values (activation,
+        // state load): lay the chosen kit back over the macros it still covers. That
+        // needs the override's lock, so it happens at the top of the next block.
+        self.applied_kit = self.params.master.kit.value();
+        self.restore_kit = true;
+    }
+
+    fn process(
+        &mut self,
+        buffer: &mut Buffer<'_>,
+        _aux: &mut AuxiliaryBuffers<'_>,
+        ctx: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        let samples = buffer.samples();
+        let transport = TransportSnapshot::capture(ctx.transport(), self.sample_rate);
+        self.render(buffer.as_slice(), samples, transport, || ctx.next_event())
+    }
+}
+
+// Helpers
+
+const HAT_CLOSED: usize = 3;
+const HAT_OPEN: usize = 4;
+
+/// Per-slot Haas delay: a line per channel, each gliding towards its own delay
+/// (in samples), so a side never plays the other side's history.
+struct HaasDelay {
+    lines: [DelayLine; 2],
+    delay: [f32; 2],
+    coef: f32,
+}
+
+impl HaasDelay {
+    fn new(sr: f32) -> Self {
+        let sr = sr.max(1.0);
+        let len = (HAAS_MAX_MS * 0.001 * sr).ceil() as usize;
+        Self {
+            lines: [DelayLine::new(len), DelayLine::new(len)],
+            delay: [0.0; 2],
+            coef: 1.0 - (-1.0 / (HAAS_GLIDE_SEC * sr)).exp(),
+        }
+    }
+
+    fn reset(&mut self) {
+        for line in &mut self.lines {
+            line.reset();
+        }
+        self.delay = [0.0; 2];
+    }
+
+    #[inline]
+    fn process(&mut self, l: f32, r: f32, delay_l: f32, delay_r: f32) -> (f32, f32) {
+        self.delay[0] = flush_denormals(self.delay[0] + self.coef * (delay_l - self.delay[0]));
+        self.delay[1] = flush_denormals(self.delay[1] + self.coef * (delay_r - self.delay[1]));
+        (
+            self.lines[0].process(l, self.delay[0]),
+            self.lines[1].process(r, self.delay[1]),
+        )
+    }
+}
+
+/// Pan laws, indexed by the master `pan_law` param; a slot's `pan_law` offsets by one
+/// to make room for "Master".
+/// Equal power keeps the stereo power constant; "Mono Sum" keeps L + R constant,
+/// so panning never shifts a slot's level in a mono fold-down.
+pub(crate) const PAN_LAWS: [&str; 3] = ["Equal Power", "-4.5 dB", "Mono Sum"];
+
+fn pan_to_gains(pan: f32, law: usize) -> (f32, f32) {
+    let x = (pan + 1.0) * 0.5; // 0..1
+    let theta = x * std::f32::consts::FRAC_PI_2;
+    let (cos, sin) = (theta.cos(), theta.sin());
+    match law {
+        // Mono sum (linear): -6 dB at center, L + R is 1 at every position
+        2 => (1.0 - x, x),
+        // Halfway between the two (in dB)
+        1 => (((1.0 - x) * cos).sqrt(), (x * sin).sqrt()),
+        // Equal power: -3 dB at center, constant power
+        _ => (cos, sin),
+    }
+}
 
 /// Fixed mapping from MIDI notes to slot indices.
 fn note_to_slot(note: u8) -> Option<usize> {
@@ -202,39 +1381,161 @@ fn note_to_slot(note: u8) -> Option<usize> {
     }
 }
 
-/// Return the DrumSlotParams for a slot index.
-fn match_slot_params<'a>(index: usize, params: &'a DrumParams) -> &'a DrumSlotParams {
-    match index {
-        0 => &params.kick,
-        1 => &params.snare,
-        2 => &params.clap,
-        3 => &params.hat_closed,
-        4 => &params.hat_open,
-        5 => &params.tom,
-        6 => &params.perc1,
-        7 => &params.perc2,
-        _ => &params.kick,
+/// Trigger-probability RNG state for a slot seed; kept apart from the slot's own
+/// noise sequence so probability doesn't change how the hits that play sound.
+fn chance_seed(seed: u32) -> u32 {
+    seed ^ 0x9e37_79b9
+}
+
+/// Per-note pitch offset (semitones), so the three GM tom notes play a low, mid
+/// and high tom from the one Tom slot.
+fn note_pitch(note: u8) -> f32 {
+    match note {
+        43 => -5.0, // Lowest of the three (GM High Floor Tom)
+        47 => 5.0,  // Highest (GM Low-Mid Tom)
+        _ => 0.0,   // Middle (45, GM Low Tom) and every other slot
+    }
+}
+
+// Master drive
+
+/// Drive models, indexed by the master `drive_model` param.
+pub(crate) const DRIVE_MODELS: [&str; 3] = ["Tanh", "Tape", "Transistor"];
+
+/// Per-channel state for the drive models that need it.
+#[derive(Clone, Copy, Default)]
+struct DriveChannel {
+    /// Tape high-frequency loss
+    lp: f64,
+    /// DC blocker after the asymmetric transistor curve
+    dc_x1: f64,
+    dc_y1: f64,
+}
+
+struct MasterDrive {
+    sr: f64,
+    ch: [DriveChannel; 2],
+    dc_coeff: f64,
+}
+
+impl MasterDrive {
+    fn new(sr: f32) -> Self {
+        let sr = sr.max(1.0) as f64;
+        Self {
+            sr,
+            ch: [DriveChannel::default(); 2],
+            // ~20 Hz high-pass
+            dc_coeff: (-2.0 * std::f64::consts::PI * 20.0 / sr).exp(),
+        }
+    }
+
+    fn process(&mut self, l: f64, r: f64, drive: f32, model: usize) -> (f64, f64) {
+        if drive <= 0.0 {
+            return (l, r);
+        }
+
+        let drive = drive as f64;
+        let drive_gain = 1.0 + drive * 4.0;
+        let makeup = 1.0 / (1.0 + drive * 2.0);
+        match model {
+            // Tape: softer knee, and the top end rolls off as it's pushed
+            1 => {
+                let fc = (16000.0 - 8000.0 * drive).min(self.sr * 0.45);
+                let alpha = 1.0 - (-2.0 * std::f64::consts::PI * fc / self.sr).exp();
+                let [cl, cr] = &mut self.ch;
+                (
+                    Self::tape(cl, l * drive_gain, alpha) * makeup,
+                    Self::tape(cr, r * drive_gain, alpha) * makeup,
+                )
+            }
+            // Transistor: negative swings clip harder; the offset that leaves is blocked
+            2 => {
+                let coeff = self.dc_coeff;
+                let [cl, cr] = &mut self.ch;
+                (
+                    Self::transistor(cl, l * drive_gain, coeff) * makeup,
+                    Self::transistor(cr, r * drive_gain, coeff) * makeup,
+                )
+            }
+            _ => (
+                fast_tanh_f64(l * drive_gain) * makeup,
+                fast_tanh_f64(r * drive_gain) * makeup,
+            ),
+        }
+    }
+
+    #[inline]
+    fn tape(ch: &mut DriveChannel, x: f64, alpha: f64) -> f64 {
+        let y = x / (1.0 + x.abs());
+        ch.lp += alpha * (y - ch.lp);
+        ch.lp = flush_denormals_f64(ch.lp);
+        ch.lp
+    }
+
+    #[inline]
+    fn transistor(ch: &mut DriveChannel, x: f64, dc_coeff: f64) -> f64 {
+        let y = if x >= 0.0 {
+            fast_tanh_f64(x)
+        } else {
+            fast_tanh_f64(x * 1.6) / 1.6
+        };
+        let out = y - ch.dc_x1 + dc_coeff * ch.dc_y1;
+        ch.dc_x1 = y;
+        ch.dc_y1 = flush_denormals_f64(out);
+        out
     }
 }
 
 // Simple bus compressor
 
-struct SimpleComp {
-    sr: f32,
+/// Envelope and gain state for one detection channel.
+#[derive(Clone, Copy)]
+struct CompDetector {
     env: f32,
     gain_smooth: f32,
+    sustain: f32,
+}
+
+impl Default for CompDetector {
+    fn default() -> Self {
+        Self {
+            env: 0.0,
+            gain_smooth: 1.0,
+            sustain: 0.0,
+        }
+    }
+}
+
+struct SimpleComp {
+    sr: f32,
+    /// Linked uses the first detector for both channels; dual mono one each.
+    det: [CompDetector; 2],
+    linked: bool,
+    atk_ms: f32,
+    rel_ms: f32,
     atk_coeff: f32,
     rel_coeff: f32,
+
+    // Auto release: `sustain` tracks how continuously the input sits above
+    // threshold and blends the release between a fast and a slow coefficient.
+    sustain_coeff: f32,
+    rel_fast_coeff: f32,
+    rel_slow_coeff: f32,
 }
 
 impl SimpleComp {
     fn new(sr: f32) -> Self {
         let mut s = Self {
             sr: sr.max(1.0),
-            env: 0.0,
-            gain_smooth: 1.0,
+            det: [CompDetector::default(); 2],
+            linked: true,
+            atk_ms: COMP_ATTACK_MS,
+            rel_ms: COMP_RELEASE_MS,
             atk_coeff: 0.0,
             rel_coeff: 0.0,
+            sustain_coeff: 0.0,
+            rel_fast_coeff: 0.0,
+            rel_slow_coeff: 0.0,
         };
         s.update_time_constants();
         s
@@ -246,38 +1547,98 @@ impl SimpleComp {
     }
 
     fn reset(&mut self) {
-        self.env = 0.0;
-        self.gain_smooth = 1.0;
+        self.det = [CompDetector::default(); 2];
+    }
+
+    /// Stereo-linked (default) or dual-mono detection; cheap to call every sample.
+    fn set_linked(&mut self, linked: bool) {
+        if linked != self.linked {
+            self.linked = linked;
+            // Both channels continue from the shared state
+            self.det[1] = self.det[0];
+        }
+    }
+
+    /// Attack and release in ms; coefficients are only recomputed on a change.
+    fn set_times(&mut self, atk_ms: f32, rel_ms: f32) {
+        if atk_ms != self.atk_ms || rel_ms != self.rel_ms {
+            self.atk_ms = atk_ms;
+            self.rel_ms = rel_ms;
+            self.update_time_constants();
+        }
     }
 
     fn update_time_constants(&mut self) {
-        // Simple fixed times
-        let atk_ms = 5.0;
-        let rel_ms = 80.0;
-        self.atk_coeff = (-1.0 / ((atk_ms / 1000.0) * self.sr)).exp();
-        self.rel_coeff = (-1.0 / ((rel_ms / 1000.0) * self.sr)).exp();
+        let coeff = |ms: f32| (-1.0 / ((ms / 1000.0) * self.sr)).exp();
+
+        self.atk_coeff = coeff(self.atk_ms.max(0.01));
+        self.rel_coeff = coeff(self.rel_ms.max(0.01));
+
+        // Auto release: 30 ms after transients, up to 400 ms on sustained overshoot
+        self.rel_fast_coeff = coeff(30.0);
+        self.rel_slow_coeff = coeff(400.0);
+        self.sustain_coeff = coeff(300.0);
     }
 
-    fn process(&mut self, l: f32, r: f32, amount: f32) -> (f32, f32) {
+    /// `mix` blends the compressed signal with the dry input (1 = fully compressed).
+    /// The signal stays f64; only the detector, which sets a gain, runs in f32.
+    fn process(&mut self, l: f64, r: f64, amount: f32, auto_release: bool, mix: f32) -> (f64, f64) {
+        let (gl, gr) = self.gains(l, r, amount, auto_release, mix);
+        (l * gl, r * gr)
+    }
+
+    /// Left and right gains `process` would apply to `l`/`r`, for a caller that
+    /// spreads them over more than the signal the detector heard.
+    fn gains(&mut self, l: f64, r: f64, amount: f32, auto_release: bool, mix: f32) -> (f64, f64) {
         let amt = amount.clamp(0.0, 1.0);
         if amt <= 0.001 {
-            return (l, r);
+            return (1.0, 1.0);
         }
 
-        let x = l.abs().max(r.abs());
+        let mix = mix.clamp(0.0, 1.0);
+        if self.linked {
+            let g = self.detect(0, l.abs().max(r.abs()) as f32, amt, auto_release);
+            let g = (1.0 + (g - 1.0) * mix) as f64;
+            (g, g)
+        } else {
+            let gl = self.detect(0, l.abs() as f32, amt, auto_release);
+            let gr = self.detect(1, r.abs() as f32, amt, auto_release);
+            (
+                (1.0 + (gl - 1.0) * mix) as f64,
+                (1.0 + (gr - 1.0) * mix) as f64,
+            )
+        }
+    }
+
+    /// Run detector `ch` on level `x`; returns its smoothed gain.
+    fn detect(&mut self, ch: usize, x: f32, amt: f32, auto_release: bool) -> f32 {
+        let thr_db = -12.0;
+        let d = &mut self.det[ch];
+
         let target = x;
 
-        if target > self.env {
-            self.env = self.atk_coeff * self.env + (1.0 - self.atk_coeff) * target;
+        let rel_coeff = if auto_release {
+            let over = if 20.0 * x.max(1e-8).log10() > thr_db {
+                1.0
+            } else {
+                0.0
+            };
+            d.sustain = self.sustain_coeff * d.sustain + (1.0 - self.sustain_coeff) * over;
+            self.rel_fast_coeff + (self.rel_slow_coeff - self.rel_fast_coeff) * d.sustain
+        } else {
+            self.rel_coeff
+        };
+
+        if target > d.env {
+            d.env = self.atk_coeff * d.env + (1.0 - self.atk_coeff) * target;
         } else {
-            self.env = self.rel_coeff * self.env + (1.0 - self.rel_coeff) * target;
+            d.env = rel_coeff * d.env + (1.0 - rel_coeff) * target;
         }
 
         let eps = 1e-8;
-        let level_lin = (self.env).max(eps);
+        let level_lin = (d.env).max(eps);
         let level_db = 20.0 * level_lin.log10();
 
-        let thr_db = -12.0;
         let ratio = 1.0 + 3.0 * amt; // 1..4
         let mut gain_db = 0.0;
 
@@ -291,130 +1652,2841 @@ impl SimpleComp {
 
         // Smooth gain to avoid zipper noise
         let g_smooth_coeff = 0.5;
-        self.gain_smooth = self.gain_smooth * g_smooth_coeff + target_gain * (1.0 - g_smooth_coeff);
+        d.gain_smooth = d.gain_smooth * g_smooth_coeff + target_gain * (1.0 - g_smooth_coeff);
 
-        let g = self.gain_smooth;
-        (l * g, r * g)
+        // Parallel blend is applied by the caller by interpolating this gain
+        d.gain_smooth
     }
 }
 
-// Simple stereo room-ish reverb
+// Bass mono crossover
 
-struct SimpleReverb {
+/// Linkwitz-Riley (two cascaded Butterworth) highpass on the side channel: below
+/// the crossover the side is removed, so the lows end up mono, while the mid (and
+/// so identical L/R) passes through unchanged.
+struct BassMono {
     sr: f32,
-    buf_l: Vec<f32>,
-    buf_r: Vec<f32>,
-    idx: usize,
-    d1_l: usize,
-    d2_l: usize,
-    d1_r: usize,
-    d2_r: usize,
-    feedback: f32,
+    freq: f32,
+    side_hp: [ZdfSvf; 2],
 }
 
-impl SimpleReverb {
+impl BassMono {
     fn new(sr: f32) -> Self {
-        let mut s = Self {
-            sr: sr.max(1.0),
-            buf_l: Vec::new(),
-            buf_r: Vec::new(),
-            idx: 0,
-            d1_l: 0,
-            d2_l: 0,
-            d1_r: 0,
-            d2_r: 0,
-            feedback: 0.4,
-        };
-        s.set_sample_rate(sr);
-        s
+        Self {
+            sr,
+            freq: 0.0,
+            side_hp: [ZdfSvf::new(sr), ZdfSvf::new(sr)],
+        }
     }
 
-    fn set_sample_rate(&mut self, sr: f32) {
-        self.sr = sr.max(1.0);
-        self.init_buffers();
+    fn process(&mut self, l: f32, r: f32, freq: f32) -> (f32, f32) {
+        if freq < 20.0 {
+            self.freq = 0.0;
+            return (l, r);
+        }
+
+        if freq != self.freq {
+            self.freq = freq;
+            let fc = freq.min(self.sr * 0.45);
+            for f in &mut self.side_hp {
+                f.set(fc, std::f32::consts::FRAC_1_SQRT_2, FilterMode::HP);
+            }
+        }
+
+        let mid = (l + r) * 0.5;
+        let side = self.side_hp[0].process((l - r) * 0.5);
+        let side = self.side_hp[1].process(side);
+
+        (mid + side, mid - side)
+    }
+}
+
+// Stereoize
+
+/// Allpass delays (s) for the stereoize decorrelator; short enough not to smear.
+const STEREOIZE_DELAYS: [f32; 3] = [0.0031, 0.0053, 0.0079];
+
+/// Widens a centred kit by adding an allpassed copy of the mid as side. The
+/// side cancels in the mono sum, so mono playback is untouched.
+struct Stereoize {
+    aps: [Allpass; 3],
+}
+
+impl Stereoize {
+    fn new(sr: f32) -> Self {
+        Self {
+            aps: core::array::from_fn(|i| Allpass::new((STEREOIZE_DELAYS[i] * sr) as usize, 0.5)),
+        }
     }
 
     fn reset(&mut self) {
-        for x in &mut self.buf_l {
-            *x = 0.0;
+        for ap in &mut self.aps {
+            ap.reset();
         }
-        for x in &mut self.buf_r {
-            *x = 0.0;
+    }
+
+    fn process(&mut self, l: f32, r: f32, amount: f32) -> (f32, f32) {
+        if amount <= 0.0 {
+            return (l, r);
         }
-        self.idx = 0;
+        let mid = (l + r) * 0.5;
+        let side = (l - r) * 0.5;
+        let diffuse = self.aps.iter_mut().fold(mid, |x, ap| ap.process(x));
+        let side = side + diffuse * amount * 0.5;
+        (mid + side, mid - side)
     }
+}
 
-    fn init_buffers(&mut self) {
-        // Max ~250 ms buffer
-        let max_time = 0.25;
-        let len = (self.sr * max_time).round().max(1.0) as usize;
+// Correlation meter
 
-        self.buf_l = vec![0.0; len];
-        self.buf_r = vec![0.0; len];
-        self.idx = 0;
+/// Averaging time of the correlation meter.
+const CORRELATION_SEC: f32 = 0.3;
 
-        // Set a couple of tap delays for a small-room feel
-        self.d1_l = ((0.031 * self.sr) as usize).min(len - 1);
-        self.d2_l = ((0.053 * self.sr) as usize).min(len - 1);
-        self.d1_r = ((0.037 * self.sr) as usize).min(len - 1);
-        self.d2_r = ((0.061 * self.sr) as usize).min(len - 1);
+/// Running normalized cross-correlation of L and R: smoothed L·R over the
+/// geometric mean of the smoothed channel powers.
+struct CorrelationMeter {
+    coef: f32,
+    lr: f32,
+    ll: f32,
+    rr: f32,
+}
 
-        self.feedback = 0.4;
+impl CorrelationMeter {
+    fn new(sr: f32) -> Self {
+        Self {
+            coef: 1.0 - (-1.0 / (CORRELATION_SEC * sr.max(1.0))).exp(),
+            lr: 0.0,
+            ll: 0.0,
+            rr: 0.0,
+        }
     }
 
-    fn process(&mut self, l: f32, r: f32, amount: f32) -> (f32, f32) {
-        let amt = amount.clamp(0.0, 1.0);
-        if amt <= 0.001 || self.buf_l.is_empty() {
-            return (l, r);
+    #[inline]
+    fn process(&mut self, l: f32, r: f32) {
+        self.lr += self.coef * (l * r - self.lr);
+        self.ll += self.coef * (l * l - self.ll);
+        self.rr += self.coef * (r * r - self.rr);
+    }
+
+    fn value(&self) -> f32 {
+        let power = (self.ll * self.rr).sqrt();
+        if power < SILENCE * SILENCE {
+            return 0.0;
         }
+        (self.lr / power).clamp(-1.0, 1.0)
+    }
+}
 
-        let len = self.buf_l.len();
-        let idx = self.idx;
+// Output meter
 
-        let in_mono = (l + r) * 0.5;
+/// Output level meter: one-pole follower on the louder channel, with attack and
+/// release per METER_BALLISTICS; power modes follow x² and report its root.
+struct LevelMeter {
+    sr: f32,
+    mode: usize,
+    attack: f32,
+    release: f32,
+    power: bool,
+    level: f32,
+}
 
-        // Read taps
-        let tap_idx = |i: usize, d: usize, len: usize| (i + len - d) % len;
-        let y1_l = self.buf_l[tap_idx(idx, self.d1_l, len)];
-        let y2_l = self.buf_l[tap_idx(idx, self.d2_l, len)];
-        let y1_r = self.buf_r[tap_idx(idx, self.d1_r, len)];
-        let y2_r = self.buf_r[tap_idx(idx, self.d2_r, len)];
+impl LevelMeter {
+    fn new(sr: f32) -> Self {
+        let mut meter = Self {
+            sr: sr.max(1.0),
+            mode: 0,
+            attack: 1.0,
+            release: 1.0,
+            power: false,
+            level: 0.0,
+        };
+        meter.update();
+        meter
+    }
 
-        let wet_l = 0.7 * y1_l + 0.3 * y2_l;
-        let wet_r = 0.7 * y1_r + 0.3 * y2_r;
+    /// Switch ballistics; the current reading carries over.
+    fn set_mode(&mut self, mode: usize) {
+        let mode = mode.min(METER_BALLISTICS.len() - 1);
+        if mode != self.mode {
+            let level = self.value();
+            self.mode = mode;
+            self.update();
+            self.level = if self.power { level * level } else { level };
+        }
+    }
 
-        // Write new value with feedback
-        self.buf_l[idx] = in_mono + wet_l * self.feedback;
-        self.buf_r[idx] = in_mono + wet_r * self.feedback;
+    fn update(&mut self) {
+        let coef = |sec: f32| {
+            if sec <= 0.0 {
+                1.0
+            } else {
+                1.0 - (-1.0 / (sec * self.sr)).exp()
+            }
+        };
+        let (attack, release, power) = METER_BALLISTICS[self.mode];
+        self.attack = coef(attack);
+        self.release = coef(release);
+        self.power = power;
+    }
 
-        self.idx = (idx + 1) % len;
+    #[inline]
+    fn process(&mut self, l: f32, r: f32) {
+        let x = l.abs().max(r.abs());
+        let x = if self.power { x * x } else { x };
+        let coef = if x > self.level {
+            self.attack
+        } else {
+            self.release
+        };
+        self.level = flush_denormals(self.level + coef * (x - self.level));
+    }
 
-        let dry_mul = 1.0 - amt * 0.6;
-        let wet_mul = amt;
+    /// Fall through `samples` of silence at once.
+    fn skip(&mut self, samples: usize) {
+        self.level *= (1.0 - self.release).powi(samples as i32);
+    }
 
-        let out_l = l * dry_mul + wet_l * wet_mul;
-        let out_r = r * dry_mul + wet_r * wet_mul;
+    fn value(&self) -> f32 {
+        if self.power {
+            self.level.sqrt()
+        } else {
+            self.level
+        }
+    }
+}
 
-        (out_l, out_r)
+/// Corner of the master "Air" shelf.
+const AIR_HZ: f32 = 8000.0;
+
+/// Gentle high shelf on the whole kit; bypassed at 0 dB.
+struct AirShelf {
+    sr: f32,
+    gain_db: f32,
+    l: Biquad,
+    r: Biquad,
+}
+
+impl AirShelf {
+    fn new(sr: f32) -> Self {
+        Self {
+            sr,
+            gain_db: 0.0,
+            l: Biquad::default(),
+            r: Biquad::default(),
+        }
+    }
+
+    fn process(&mut self, l: f32, r: f32, gain_db: f32) -> (f32, f32) {
+        if gain_db <= 0.0 {
+            self.gain_db = 0.0;
+            return (l, r);
+        }
+
+        if gain_db != self.gain_db {
+            if self.gain_db == 0.0 {
+                // Re-engaging: don't resume from a stale state
+                self.l.reset();
+                self.r.reset();
+            }
+            self.gain_db = gain_db;
+            self.l.high_shelf(self.sr, AIR_HZ, gain_db);
+            self.r.high_shelf(self.sr, AIR_HZ, gain_db);
+        }
+
+        (self.l.process(l), self.r.process(r))
     }
 }
 
-// CLAP metadata
+// Simple stereo room-ish reverb
 
-impl ClapPlugin for Drumini {
-    const CLAP_ID: &'static str = "dev.example.drumini";
-    const CLAP_DESCRIPTION: Option<&'static str> =
-        Some("Minimalist electronic drum synth for beginners.");
-    const CLAP_FEATURES: &'static [ClapFeature] = &[
-        ClapFeature::Instrument,
-        ClapFeature::Drum,
-        ClapFeature::Stereo,
-    ];
+/// Pre-verb filter ranges; at these ends the filter is out of circuit.
+pub(crate) const PRE_VERB_HP_MIN: f32 = 20.0;
+pub(crate) const PRE_VERB_LP_MAX: f32 = 20000.0;
+const BUTTERWORTH_Q: f32 = core::f32::consts::FRAC_1_SQRT_2;
 
-    fn remote_controls(&self, _context: &mut impl RemoteControlsContext) {}
+/// Longest early reflection the buffer holds.
+const REVERB_ER_SEC: f32 = 0.25;
 
-    const CLAP_MANUAL_URL: Option<&'static str> = Some("Not yet");
-    const CLAP_SUPPORT_URL: Option<&'static str> = Some("Not yet");
+/// Most taps any early-reflection pattern uses per channel.
+const MAX_ER_TAPS: usize = 4;
+
+/// Early-reflection pattern: (time in seconds, gain) taps per channel.
+pub(crate) struct ErPattern {
+    pub name: &'static str,
+    left: &'static [(f32, f32)],
+    right: &'static [(f32, f32)],
 }
 
-nih_export_clap!(Drumini);
+pub(crate) const ER_PATTERNS: &[ErPattern] = &[
+    ErPattern {
+        name: "Tight Room",
+        left: &[(0.031, 0.7), (0.053, 0.3)],
+        right: &[(0.037, 0.7), (0.061, 0.3)],
+    },
+    ErPattern {
+        name: "Plate",
+        left: &[(0.011, 0.35), (0.019, 0.3), (0.029, 0.2), (0.041, 0.15)],
+        right: &[(0.013, 0.35), (0.023, 0.3), (0.031, 0.2), (0.047, 0.15)],
+    },
+    ErPattern {
+        name: "Hall",
+        left: &[(0.061, 0.4), (0.097, 0.3), (0.149, 0.2), (0.211, 0.1)],
+        right: &[(0.071, 0.4), (0.113, 0.3), (0.163, 0.2), (0.227, 0.1)],
+    },
+    ErPattern {
+        name: "Ambience",
+        left: &[(0.007, 0.45), (0.013, 0.3), (0.017, 0.25)],
+        right: &[(0.009, 0.45), (0.011, 0.3), (0.019, 0.25)],
+    },
+];
+
+/// Taps resolved to sample delays for one channel.
+#[derive(Clone, Copy, Default)]
+struct Taps {
+    delays: [usize; MAX_ER_TAPS],
+    gains: [f32; MAX_ER_TAPS],
+    count: usize,
+}
+
+impl Taps {
+    fn resolve(pattern: &[(f32, f32)], sr: f32, len: usize) -> Self {
+        let mut taps = Self::default();
+        let mut prev = 0;
+        for (i, &(time, gain)) in pattern.iter().take(MAX_ER_TAPS).enumerate() {
+            // Patterns are sorted by time; keep each tap at least a sample past the
+            // last so low sample rates can't collapse them onto one index
+            let delay = ((time * sr) as usize).max(prev + 1).min(len - 1);
+            prev = delay;
+            taps.delays[i] = delay;
+            taps.gains[i] = gain;
+            taps.count = i + 1;
+        }
+        taps
+    }
+
+    #[inline]
+    fn read(&self, buf: &[f64], idx: usize) -> f64 {
+        let len = buf.len();
+        let mut y = 0.0;
+        for i in 0..self.count {
+            y += self.gains[i] as f64 * buf[(idx + len - self.delays[i]) % len];
+        }
+        y
+    }
+}
+
+/// Feedback delay lines available to the late tail; the density param picks how many run.
+pub(crate) const FDN_MAX_LINES: usize = 4;
+
+/// Late-tail line lengths (s), mutually prime-ish so echoes don't stack.
+const FDN_DELAYS: [f32; FDN_MAX_LINES] = [0.0297, 0.0371, 0.0411, 0.0437];
+
+/// Loop damping: highs die faster than lows, like a real room.
+const FDN_DAMP_HZ: f32 = 7000.0;
+
+/// Level of the late tail against the early reflections.
+const FDN_OUT_GAIN: f32 = 0.7;
+
+/// Runaway protection: loop gain never reaches unity, and if the tail still
+/// climbs past the ceiling the feedback is halved, then eased back.
+const FDN_MAX_GAIN: f32 = 0.98;
+const FDN_CEILING: f32 = 4.0;
+const FDN_GUARD_RECOVER: f32 = 1e-4;
+
+/// Late tail: delay lines mixed through a Householder matrix, each with its own
+/// damping and a gain set from the RT60.
+struct Fdn {
+    lines: [Vec<f64>; FDN_MAX_LINES],
+    idx: [usize; FDN_MAX_LINES],
+    gains: [f64; FDN_MAX_LINES],
+    damp: [f64; FDN_MAX_LINES],
+    damp_alpha: f64,
+    active: usize,
+    rt60: f32,
+    sr: f32,
+    /// Feedback scale from the runaway watchdog (1 = untouched)
+    guard: f32,
+}
+
+impl Fdn {
+    /// A tail with no lines allocated, standing in while the reverb is bypassed; it
+    /// must not be processed.
+    fn empty(sr: f32) -> Self {
+        let sr = sr.max(1.0);
+        let mut fdn = Self {
+            lines: Default::default(),
+            idx: [0; FDN_MAX_LINES],
+            gains: [0.0; FDN_MAX_LINES],
+            damp: [0.0; FDN_MAX_LINES],
+            damp_alpha: 1.0
+                - (-2.0 * std::f64::consts::PI * (FDN_DAMP_HZ.min(sr * 0.45) / sr) as f64).exp(),
+            active: 2,
+            rt60: 0.25,
+            sr,
+            guard: 1.0,
+        };
+        fdn.update_gains();
+        fdn
+    }
+
+    /// Allocate the lines, or `None` if one would be longer than `max_len` samples
+    /// or can't be allocated.
+    fn new(sr: f32, max_len: usize) -> Option<Self> {
+        let mut fdn = Self::empty(sr);
+        for (line, &sec) in fdn.lines.iter_mut().zip(&FDN_DELAYS) {
+            let len = ((sec * fdn.sr) as usize).max(1);
+            if len > max_len {
+                return None;
+            }
+            *line = try_zeroed(len)?;
+        }
+        fdn.update_gains();
+        Some(fdn)
+    }
+
+    fn reset(&mut self) {
+        for line in &mut self.lines {
+            line.fill(0.0);
+        }
+        self.idx = [0; FDN_MAX_LINES];
+        self.damp = [0.0; FDN_MAX_LINES];
+        self.guard = 1.0;
+    }
+
+    /// Number of active lines and decay time; cheap to call every sample.
+    fn set_tail(&mut self, lines: usize, rt60: f32) {
+        let lines = lines.clamp(1, FDN_MAX_LINES);
+        if lines != self.active || rt60 != self.rt60 {
+            if lines > self.active {
+                // Lines coming back in start from silence, not a stale tail
+                for i in self.active..lines {
+                    self.lines[i].fill(0.0);
+                    self.damp[i] = 0.0;
+                }
+            }
+            self.active = lines;
+            self.rt60 = rt60;
+            self.update_gains();
+        }
+    }
+
+    fn update_gains(&mut self) {
+        let rt60 = self.rt60.max(0.01);
+        for (gain, line) in self.gains.iter_mut().zip(&self.lines) {
+            // -60 dB after rt60 seconds, whatever the line length
+            *gain = 10.0f64
+                .powf(-3.0 * line.len() as f64 / (rt60 * self.sr) as f64)
+                .min(FDN_MAX_GAIN as f64);
+        }
+    }
+
+    #[inline]
+    fn process(&mut self, x: f64, choked: bool) -> (f64, f64) {
+        let n = self.active;
+        let mut outs = [0.0f64; FDN_MAX_LINES];
+        let mut sum = 0.0;
+        for ((out, line), &idx) in outs.iter_mut().zip(&self.lines).zip(&self.idx).take(n) {
+            *out = line[idx];
+            sum += *out;
+        }
+
+        // Householder mix: y - 2/N * sum(y) is orthogonal, so it only spreads energy
+        let k = 2.0 / n as f64;
+        let (mut l, mut r) = (0.0, 0.0);
+        for (i, &y) in outs.iter().enumerate().take(n) {
+            l += y;
+            r += if i % 2 == 0 { y } else { -y };
+
+            self.damp[i] += self.damp_alpha * ((y - k * sum) - self.damp[i]);
+            self.damp[i] = flush_denormals_f64(self.damp[i]);
+            let feedback = if choked {
+                0.0
+            } else {
+                self.gains[i] * self.guard as f64
+            };
+            let len = self.lines[i].len();
+            self.lines[i][self.idx[i]] = x + self.damp[i] * feedback;
+            self.idx[i] = (self.idx[i] + 1) % len;
+        }
+
+        let norm = (FDN_OUT_GAIN / (n as f32).sqrt()) as f64;
+        let (l, r) = (l * norm, r * norm);
+
+        if l.abs().max(r.abs()) > FDN_CEILING as f64 {
+            self.guard *= 0.5;
+        } else if self.guard < 1.0 {
+            self.guard = (self.guard + FDN_GUARD_RECOVER).min(1.0);
+        }
+        (l, r)
+    }
+}
+
+/// Early reflections from a multi-tap line, then an FDN for the late tail.
+struct SimpleReverb {
+    sr: f32,
+    er_buf: Vec<f64>,
+    idx: usize,
+    pattern: usize,
+    taps_l: Taps,
+    taps_r: Taps,
+    fdn: Fdn,
+    /// Feedback muted so the tail dies after the last echo
+    choked: bool,
+    /// The buffers couldn't be allocated; the reverb passes the dry signal
+    bypassed: bool,
+    /// Longest buffer, in samples, the reverb will try to allocate. Tests lower it
+    /// to take the bypass path.
+    max_len: usize,
+    // Pre-verb EQ on the send, and the cutoffs it was last set to
+    pre_hp: Biquad,
+    pre_lp: Biquad,
+    pre_hz: (f32, f32),
+}
+
+impl SimpleReverb {
+    fn new(sr: f32) -> Self {
+        let mut s = Self {
+            sr: sr.max(1.0),
+            er_buf: Vec::new(),
+            idx: 0,
+            pattern: 0,
+            taps_l: Taps::default(),
+            taps_r: Taps::default(),
+            fdn: Fdn::empty(sr),
+            choked: false,
+            bypassed: false,
+            max_len: usize::MAX,
+            pre_hp: Biquad::default(),
+            pre_lp: Biquad::default(),
+            pre_hz: (PRE_VERB_HP_MIN, PRE_VERB_LP_MAX),
+        };
+        s.set_sample_rate(sr);
+        s
+    }
+
+    /// Returns false if the buffers couldn't be allocated and the reverb is bypassed.
+    fn set_sample_rate(&mut self, sr: f32) -> bool {
+        self.sr = sr.max(1.0);
+        self.init_buffers();
+        let (hp, lp) = self.pre_hz;
+        self.pre_hp.high_pass(self.sr, hp, BUTTERWORTH_Q);
+        self.pre_lp.low_pass(self.sr, lp, BUTTERWORTH_Q);
+        !self.bypassed
+    }
+
+    fn reset(&mut self) {
+        self.er_buf.fill(0.0);
+        self.idx = 0;
+        self.fdn.reset();
+        self.pre_hp.reset();
+        self.pre_lp.reset();
+    }
+
+    fn init_buffers(&mut self) {
+        let len = ((self.sr * REVERB_ER_SEC).round() as usize).max(MAX_ER_TAPS + 1);
+        let (lines, rt60) = (self.fdn.active, self.fdn.rt60);
+
+        // Fallible, so an absurd rate or a tight target bypasses the room instead of
+        // aborting; bypassed, the reverb holds no buffers at all
+        let er_buf = if len <= self.max_len {
+            try_zeroed(len)
+        } else {
+            None
+        };
+        match (er_buf, Fdn::new(self.sr, self.max_len)) {
+            (Some(er_buf), Some(fdn)) => {
+                self.er_buf = er_buf;
+                self.fdn = fdn;
+                self.bypassed = false;
+            }
+            _ => {
+                self.er_buf = Vec::new();
+                self.fdn = Fdn::empty(self.sr);
+                self.bypassed = true;
+            }
+        }
+        self.fdn.set_tail(lines, rt60);
+        self.idx = 0;
+
+        self.update_taps();
+    }
+
+    fn set_choked(&mut self, choked: bool) {
+        self.choked = choked;
+    }
+
+    /// Select an early-reflection pattern; cheap to call every sample.
+    fn set_pattern(&mut self, pattern: usize) {
+        let pattern = pattern.min(ER_PATTERNS.len() - 1);
+        if pattern != self.pattern {
+            self.pattern = pattern;
+            self.update_taps();
+        }
+    }
+
+    /// Pre-verb high- and low-pass cutoffs (Hz); each is bypassed at its range end.
+    fn set_pre_filter(&mut self, hp_hz: f32, lp_hz: f32) {
+        if (hp_hz, lp_hz) != self.pre_hz {
+            self.pre_hz = (hp_hz, lp_hz);
+            self.pre_hp.high_pass(self.sr, hp_hz, BUTTERWORTH_Q);
+            self.pre_lp.low_pass(self.sr, lp_hz, BUTTERWORTH_Q);
+        }
+    }
+
+    /// Tail density (active FDN lines) and decay time in seconds.
+    fn set_tail(&mut self, density: usize, decay: f32) {
+        self.fdn.set_tail(density, decay);
+    }
+
+    /// Pattern, tail and pre-verb filter from the master params, Eco included;
+    /// cheap to call every sample.
+    fn configure(&mut self, master: &MasterParams) {
+        self.set_pattern(master.reverb_pattern.value() as usize);
+        // Eco: the cheapest tail whatever the density asks for
+        let density = if master.eco.value() {
+            1
+        } else {
+            master.reverb_density.value() as usize
+        };
+        self.set_tail(density, master.reverb_decay.value());
+        self.set_pre_filter(master.reverb_hp.value(), master.reverb_lp.value());
+    }
+
+    fn update_taps(&mut self) {
+        let len = self.er_buf.len().max(1);
+        let pattern = &ER_PATTERNS[self.pattern];
+        self.taps_l = Taps::resolve(pattern.left, self.sr, len);
+        self.taps_r = Taps::resolve(pattern.right, self.sr, len);
+    }
+
+    /// Mix the room into `l`/`r`, fed from the separate `send_l`/`send_r` bus.
+    fn process(&mut self, l: f64, r: f64, send_l: f64, send_r: f64, amount: f32) -> (f64, f64) {
+        let amt = amount.clamp(0.0, 1.0) as f64;
+        if amt <= 0.001 || self.bypassed || self.er_buf.is_empty() {
+            return (l, r);
+        }
+
+        let len = self.er_buf.len();
+        let idx = self.idx;
+
+        let mut in_mono = (send_l + send_r) * 0.5;
+        let (hp, lp) = self.pre_hz;
+        if hp > PRE_VERB_HP_MIN {
+            in_mono = self.pre_hp.process_f64(in_mono);
+        }
+        if lp < PRE_VERB_LP_MAX {
+            in_mono = self.pre_lp.process_f64(in_mono);
+        }
+
+        // Early reflections
+        let early_l = self.taps_l.read(&self.er_buf, idx);
+        let early_r = self.taps_r.read(&self.er_buf, idx);
+        self.er_buf[idx] = in_mono;
+        self.idx = (idx + 1) % len;
+
+        // Late tail
+        let (late_l, late_r) = self.fdn.process(in_mono, self.choked);
+
+        let wet_l = early_l + late_l;
+        let wet_r = early_r + late_r;
+
+        let dry_mul = 1.0 - amt * 0.6;
+        let wet_mul = amt;
+
+        let out_l = l * dry_mul + wet_l * wet_mul;
+        let out_r = r * dry_mul + wet_r * wet_mul;
+
+        (out_l, out_r)
+    }
+}
+
+// CLAP metadata
+
+impl ClapPlugin for Drumini {
+    const CLAP_ID: &'static str = "dev.example.drumini";
+    const CLAP_DESCRIPTION: Option<&'static str> =
+        Some("Minimalist electronic drum synth for beginners.");
+    const CLAP_FEATURES: &'static [ClapFeature] = &[
+        ClapFeature::Instrument,
+        ClapFeature::Drum,
+        ClapFeature::Stereo,
+    ];
+
+    fn remote_controls(&self, _context: &mut impl RemoteControlsContext) {}
+
+    const CLAP_MANUAL_URL: Option<&'static str> = Some("Not yet");
+    const CLAP_SUPPORT_URL: Option<&'static str> = Some("Not yet");
+}
+
+nih_export_clap!(Drumini);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::analysis;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    const SR: f32 = 48000.0;
+    const BLOCK: usize = 256;
+
+    /// Counts heap allocations per thread, so a test can check the audio path makes none.
+    struct CountingAlloc;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    /// Stands in for the host's editor context: records every normalized value set.
+    #[derive(Default)]
+    struct TestGui {
+        set: std::sync::Mutex<Vec<(ParamPtr, f32)>>,
+    }
+
+    impl GuiContext for TestGui {
+        fn plugin_api(&self) -> PluginApi {
+            PluginApi::Clap
+        }
+        fn request_resize(&self) -> bool {
+            false
+        }
+        unsafe fn raw_begin_set_parameter(&self, _param: ParamPtr) {}
+        unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+            self.set.lock().unwrap().push((param, normalized));
+        }
+        unsafe fn raw_end_set_parameter(&self, _param: ParamPtr) {}
+        fn get_state(&self) -> nih_plug::wrapper::state::PluginState {
+            unimplemented!()
+        }
+        fn set_state(&self, _state: nih_plug::wrapper::state::PluginState) {}
+    }
+
+    impl TestGui {
+        /// Plain values set since the last call, by param id, as the host would store them.
+        fn take(&self, drums: &Drumini) -> HashMap<String, f32> {
+            let ids: HashMap<ParamPtr, String> = drums
+                .params
+                .param_map()
+                .into_iter()
+                .map(|(id, ptr, _)| (ptr, id))
+                .collect();
+            self.set
+                .lock()
+                .unwrap()
+                .drain(..)
+                .map(|(ptr, normalized)| {
+                    (ids[&ptr].clone(), unsafe { ptr.preview_plain(normalized) })
+                })
+                .collect()
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+    /// Heap allocations `f` makes on this thread.
+    fn allocations(f: impl FnOnce()) -> usize {
+        let before = ALLOCATIONS.with(Cell::get);
+        f();
+        ALLOCATIONS.with(Cell::get) - before
+    }
+
+    struct TestInit;
+
+    impl InitContext<Drumini> for TestInit {
+        fn plugin_api(&self) -> PluginApi {
+            PluginApi::Clap
+        }
+        fn execute(&self, _task: ()) {}
+        fn set_latency_samples(&self, _samples: u32) {}
+        fn set_current_voice_capacity(&self, _capacity: u32) {}
+    }
+
+    /// An initialized plugin at `SR`, with `edit` applied to the default params.
+    fn plugin(edit: impl FnOnce(&mut DrumParams)) -> Drumini {
+        let mut params = DrumParams::default();
+        edit(&mut params);
+        params::settle_smoothers(&params);
+        let mut plugin = Drumini {
+            params: Arc::new(params),
+            ..Default::default()
+        };
+        let config = BufferConfig {
+            sample_rate: SR,
+            min_buffer_size: None,
+            max_buffer_size: BLOCK as u32,
+            process_mode: ProcessMode::Realtime,
+        };
+        assert!(plugin.initialize(&Drumini::AUDIO_IO_LAYOUTS[0], &config, &mut TestInit));
+        plugin.reset();
+        plugin
+    }
+
+    fn stopped() -> TransportSnapshot {
+        TransportSnapshot {
+            sample_rate: SR,
+            ..Default::default()
+        }
+    }
+
+    /// Render `frames` stereo frames in `BLOCK`-sized blocks. Event timings are
+    /// absolute frames, in order; a known song position advances block to block.
+    fn run_with(
+        plugin: &mut Drumini,
+        frames: usize,
+        mut transport: TransportSnapshot,
+        events: &[PluginNoteEvent<Drumini>],
+    ) -> [Vec<f32>; 2] {
+        let (mut l, mut r) = (vec![0.0; frames], vec![0.0; frames]);
+        for start in (0..frames).step_by(BLOCK) {
+            let end = (start + BLOCK).min(frames);
+            let mut block = events
+                .iter()
+                .filter(|e| (start..end).contains(&(e.timing() as usize)))
+                .map(|&e| retime(e, start as u32));
+            let mut outputs = [&mut l[start..end], &mut r[start..end]];
+            plugin.render(&mut outputs, end - start, transport, || block.next());
+            if let (Some(pos), Some(spb)) = (transport.pos_beats, transport.samples_per_beat()) {
+                transport.pos_beats = Some(pos + (end - start) as f64 / spb as f64);
+            }
+        }
+        [l, r]
+    }
+
+    fn run(
+        plugin: &mut Drumini,
+        frames: usize,
+        events: &[PluginNoteEvent<Drumini>],
+    ) -> [Vec<f32>; 2] {
+        run_with(plugin, frames, stopped(), events)
+    }
+
+    fn retime(mut event: PluginNoteEvent<Drumini>, start: u32) -> PluginNoteEvent<Drumini> {
+        match &mut event {
+            NoteEvent::NoteOn { timing, .. }
+            | NoteEvent::NoteOff { timing, .. }
+            | NoteEvent::MidiCC { timing, .. } => *timing -= start,
+            _ => {}
+        }
+        event
+    }
+
+    fn peak(x: &[f32]) -> f32 {
+        x.iter().fold(0.0, |m, v| m.max(v.abs()))
+    }
+
+    fn rms(x: &[f32]) -> f32 {
+        (x.iter().map(|v| v * v).sum::<f32>() / x.len().max(1) as f32).sqrt()
+    }
+
+    fn note_on(timing: u32, note: u8, velocity: f32) -> PluginNoteEvent<Drumini> {
+        NoteEvent::NoteOn {
+            timing,
+            voice_id: None,
+            channel: 0,
+            note,
+            velocity,
+        }
+    }
+
+    fn float(value: f32, min: f32, max: f32) -> FloatParam {
+        FloatParam::new("Test", value, FloatRange::Linear { min, max })
+    }
+
+    fn slots_mut(p: &mut DrumParams) -> [&mut params::DrumSlotParams; N_SLOTS] {
+        [
+            &mut p.kick,
+            &mut p.snare,
+            &mut p.clap,
+            &mut p.hat_closed,
+            &mut p.hat_open,
+            &mut p.tom,
+            &mut p.perc1,
+            &mut p.perc2,
+        ]
+    }
+
+    /// Take the master drive, compressor and reverb out of the way.
+    fn dry(p: &mut DrumParams) {
+        p.master.drive = float(0.0, 0.0, 1.0);
+        p.master.comp = float(0.0, 0.0, 1.0);
+        p.master.reverb = float(0.0, 0.0, 1.0);
+    }
+
+    /// Peak level of each `ms`-long window.
+    fn windowed_peaks(x: &[f32], ms: f32) -> Vec<f32> {
+        x.chunks((ms * 0.001 * SR) as usize).map(peak).collect()
+    }
+
+    /// Windows (of `ms`) whose peak jumps well above the one before.
+    fn onsets(x: &[f32], ms: f32) -> Vec<usize> {
+        let peaks = windowed_peaks(x, ms);
+        let first = (peaks[0] > 1e-3).then_some(0);
+        let rises = (1..peaks.len()).filter(|&i| peaks[i] > 1e-3 && peaks[i] > 1.6 * peaks[i - 1]);
+        first.into_iter().chain(rises).collect()
+    }
+
+    #[test]
+    fn dither_is_off_by_default_and_independent_per_channel() {
+        let mut quiet = plugin(|_| {});
+        let [l, r] = run(&mut quiet, 4800, &[]);
+        assert!(l.iter().chain(&r).all(|&x| x == 0.0));
+
+        let mut dithered = plugin(|p| p.master.dither = BoolParam::new("Dither", true));
+        let [l, r] = run(&mut dithered, 4800, &[]);
+        assert!(peak(&l).max(peak(&r)) <= DITHER_LSB);
+        assert!(rms(&l) > 0.3 * DITHER_LSB && rms(&r) > 0.3 * DITHER_LSB);
+        let cross = l.iter().zip(&r).map(|(a, b)| a * b).sum::<f32>()
+            / (l.len() as f32 * rms(&l) * rms(&r));
+        assert!(cross.abs() < 0.1, "channels correlate by {cross}");
+    }
+
+    #[test]
+    fn flam_adds_one_quieter_onset() {
+        let snare = |flam: f32| {
+            plugin(|p| {
+                p.snare.flam = float(flam, 0.0, 1.0);
+                p.snare.decay = float(10.0, 10.0, 2000.0);
+                dry(p);
+            })
+        };
+        let [single, _] = run(&mut snare(0.0), 24000, &[note_on(0, 38, 1.0)]);
+        assert_eq!(onsets(&single, 2.0), [0]);
+
+        // Full flam: a 40 ms gap (window 20) at 0.4 of the velocity
+        let [flammed, _] = run(&mut snare(1.0), 24000, &[note_on(0, 38, 1.0)]);
+        assert_eq!(onsets(&flammed, 2.0), [0, 20]);
+        let peaks = windowed_peaks(&flammed, 2.0);
+        assert!(peaks[20] - peaks[19] < 0.5 * peaks[0]);
+        // The copy doesn't flam again
+        assert!(peak(&flammed[19200..]) < 0.01 * peaks[0]);
+    }
+
+    #[test]
+    fn clip_flag_latches_until_read() {
+        let mut drums = plugin(|p| {
+            for slot in slots_mut(p) {
+                slot.level = float(2.0, 0.0, 2.0);
+            }
+            p.groups[0].gain = float(2.0, 0.0, 2.0);
+            p.master.sum_guard = BoolParam::new("Sum Guard", false);
+            dry(p);
+        });
+        let flag = drums.clip_flag();
+        let hits = [36, 38, 39, 42].map(|n| note_on(0, n, 1.0));
+        let [l, r] = run(&mut drums, 4800, &hits);
+        assert!(peak(&l).max(peak(&r)) > 1.0);
+        assert!(flag.load(Ordering::Relaxed));
+        assert!(drums.take_clipped());
+        assert!(!drums.take_clipped());
+
+        // Quieter hits don't set it again
+        drums.reset();
+        let [l, r] = run(&mut drums, 48000, &[note_on(0, 42, 0.3)]);
+        assert!(peak(&l).max(peak(&r)) < 1.0);
+        assert!(!drums.take_clipped());
+    }
+
+    #[test]
+    fn haas_delays_the_channel_facing_away_from_the_pan() {
+        let onset = |x: &[f32]| x.iter().position(|v| v.abs() > 1e-4).unwrap() as i32;
+        let lag = |haas: f32, pan: f32| {
+            let mut drums = plugin(|p| {
+                p.hat_closed.haas = float(haas, 0.0, 1.0);
+                p.hat_closed.pan = float(pan, -1.0, 1.0);
+                dry(p);
+            });
+            // Let the delay glide settle before the hit
+            let [l, r] = run(&mut drums, 9600, &[note_on(4800, 42, 1.0)]);
+            onset(&r) - onset(&l)
+        };
+        let half = (0.5 * HAAS_MAX_MS * 0.001 * SR) as i32;
+        assert_eq!(lag(0.0, 0.3), 0);
+        assert!((lag(0.5, -0.3) - half).abs() <= 4);
+        assert!((lag(0.5, 0.3) + half).abs() <= 4);
+    }
+
+    #[test]
+    fn muting_a_slot_fades_its_reverb_send_when_following_level() {
+        let tail = |follows: bool| {
+            let mut drums = plugin(|p| {
+                p.tom.decay = float(1500.0, 10.0, 2000.0);
+                dry(p);
+                p.master.reverb = float(0.6, 0.0, 1.0);
+                p.master.send_follows_level = BoolParam::new("Send Follows Level", follows);
+            });
+            run(&mut drums, 4800, &[note_on(0, 45, 1.0)]);
+            // Pull the fader down mid-ring; the dry is gone within the 20 ms glide
+            drums.params.tom.level.smoothed.set_target(SR, 0.0);
+            let [l, r] = run(&mut drums, 24000, &[]);
+            rms(&l[4800..]) + rms(&r[4800..])
+        };
+        let (following, fixed) = (tail(true), tail(false));
+        assert!(fixed > 0.0);
+        assert!(
+            following < 0.1 * fixed,
+            "tail {following} following level vs {fixed} fixed"
+        );
+    }
+
+    #[test]
+    fn changing_kit_only_retargets_smoothers() {
+        let edm = FACTORY_KITS
+            .iter()
+            .position(|k| k.name == "EDM Punch")
+            .unwrap();
+        let mut drums = plugin(|p| {
+            let max = FACTORY_KITS.len() as i32 - 1;
+            p.master.kit = IntParam::new("Kit", edm as i32, IntRange::Linear { min: 0, max });
+        });
+        run(&mut drums, BLOCK, &[]);
+        let kick_level = FACTORY_KITS[edm].slots[0][0];
+        assert_ne!(
+            drums.params.kick.level.smoothed.previous_value(),
+            kick_level
+        );
+
+        // As if the Kit param had just moved here from Init
+        drums.applied_kit = 0;
+        let (mut l, mut r) = (vec![0.0; BLOCK], vec![0.0; BLOCK]);
+        let made = allocations(|| {
+            drums.render(&mut [&mut l, &mut r], BLOCK, stopped(), || None);
+        });
+        assert_eq!(made, 0, "applying a kit allocated {made} times");
+
+        // A host reset lays the kit back over the smoothers on the audio thread too
+        let made = allocations(|| {
+            drums.reset();
+            drums.render(&mut [&mut l, &mut r], BLOCK, stopped(), || None);
+        });
+        assert_eq!(made, 0, "resetting allocated {made} times");
+
+        assert_eq!(
+            drums.params.kit_override.read().unwrap().unwrap().0,
+            edm as i32
+        );
+        run(&mut drums, 4800, &[]);
+        assert!((drums.params.kick.level.smoothed.previous_value() - kick_level).abs() < 1e-4);
+    }
+
+    #[test]
+    fn same_seeds_humanize_the_same() {
+        let groove = |seed: i32| {
+            let mut drums = plugin(|p| {
+                p.snare.humanize = float(1.0, 0.0, 1.0);
+                p.snare.seed = IntParam::new("Seed", seed, IntRange::Linear { min: 1, max: 9999 });
+            });
+            let hits: Vec<_> = (0..8).map(|i| note_on(i * 6000, 38, 0.8)).collect();
+            let take = run(&mut drums, 48000, &hits);
+            // A reset (reload) starts the sequence over
+            drums.reset();
+            assert_eq!(run(&mut drums, 48000, &hits), take);
+            take
+        };
+        assert_eq!(groove(7), groove(7));
+        assert_ne!(groove(7), groove(8));
+    }
+
+    #[test]
+    fn auto_release_recovers_fast_after_transients_and_slow_after_sustain() {
+        // Samples until the gain is back within 1 dB of unity after `burst_ms` at 0 dBFS
+        let recovery = |burst_ms: f32, auto_release: bool| {
+            let mut comp = SimpleComp::new(SR);
+            for _ in 0..(burst_ms * 0.001 * SR) as usize {
+                comp.gains(1.0, 1.0, 1.0, auto_release, 1.0);
+            }
+            (1..)
+                .find(|_| comp.gains(0.0, 0.0, 1.0, auto_release, 1.0).0 > 0.891)
+                .unwrap()
+        };
+        let (transient, sustained) = (recovery(5.0, true), recovery(500.0, true));
+        assert!(transient < recovery(5.0, false));
+        assert!(sustained > recovery(500.0, false));
+        assert!(
+            sustained > 3 * transient,
+            "{transient} vs {sustained} samples"
+        );
+    }
+
+    #[test]
+    fn parallel_blend_keeps_more_punch_at_equal_loudness() {
+        // Decaying hits four times a second
+        let hits: Vec<f64> = (0..48000)
+            .map(|i| {
+                let t = (i % 12000) as f64 / SR as f64;
+                (-t / 0.2).exp() * (2.0 * std::f64::consts::PI * 60.0 * t).sin()
+            })
+            .collect();
+        let squash = |mix: f32| -> Vec<f32> {
+            // A fast attack, so full compression catches the transients
+            let mut comp = SimpleComp::new(SR);
+            comp.set_times(0.1, COMP_RELEASE_MS);
+            hits.iter()
+                .map(|&x| comp.process(x, x, 1.0, false, mix).0 as f32)
+                .collect()
+        };
+        let crest = |x: &[f32]| peak(x) / rms(x);
+
+        // At 0 the blend is the dry input
+        let dry: Vec<f32> = hits.iter().map(|&x| x as f32).collect();
+        assert_eq!(squash(0.0), dry);
+
+        // Crest factor doesn't change with gain, so this compares at matched loudness
+        let (full, blend) = (crest(&squash(1.0)), crest(&squash(0.5)));
+        assert!(blend < crest(&dry));
+        assert!(
+            blend > full * 1.1,
+            "crest {blend} blended vs {full} fully compressed"
+        );
+    }
+
+    #[test]
+    fn parallel_blend_has_no_comb_notch() {
+        // A steady tone at half-blend: with the dry path aligned to the compressed
+        // one, every frequency comes out at the same level
+        let gain = |freq: f32| {
+            let mut comp = SimpleComp::new(SR);
+            comp.set_times(COMP_ATTACK_MS, COMP_RELEASE_MS);
+            let (input, output): (Vec<f32>, Vec<f32>) = (0..48000)
+                .map(|i| {
+                    let x = (2.0 * std::f64::consts::PI * freq as f64 * i as f64 / SR as f64).sin();
+                    (x as f32, comp.process(x, x, 1.0, false, 0.5).0 as f32)
+                })
+                .skip(24000)
+                .unzip();
+            rms(&output) / rms(&input)
+        };
+        let gains: Vec<f32> = [200.0, 1000.0, 3000.0, 7000.0, 12000.0, 18000.0]
+            .into_iter()
+            .map(gain)
+            .collect();
+        assert!(gains.iter().all(|&g| g < 0.95));
+        let (lo, hi) = gains
+            .iter()
+            .fold((f32::MAX, 0.0f32), |(lo, hi), &g| (lo.min(g), hi.max(g)));
+        assert!(hi / lo < 1.02, "blend gains {gains:?} across the band");
+    }
+
+    #[test]
+    fn more_fdn_lines_make_a_denser_smoother_tail() {
+        // Share of 1 ms slices of the impulse response's tail carrying a real echo,
+        // and the tail's crest factor: isolated spikes read high
+        let tail = |lines: usize| {
+            let mut fdn = Fdn::new(SR, usize::MAX).unwrap();
+            fdn.set_tail(lines, 1.0);
+            let out: Vec<f32> = (0..24000)
+                .map(|i| fdn.process(if i == 0 { 1.0 } else { 0.0 }, false).0 as f32)
+                .collect();
+            let slices: Vec<f32> = out[2400..19200].chunks(48).map(rms).collect();
+            let mean = slices.iter().sum::<f32>() / slices.len() as f32;
+            let dense =
+                slices.iter().filter(|&&s| s > 0.1 * mean).count() as f32 / slices.len() as f32;
+            (dense, peak(&out[2400..19200]) / rms(&out[2400..19200]))
+        };
+        let ((sparse, spiky), (dense, smooth)) = (tail(1), tail(FDN_MAX_LINES));
+        assert!(
+            dense > 4.0 * sparse,
+            "echo density {dense} vs {sparse} with one line"
+        );
+        assert!(
+            smooth < 0.5 * spiky,
+            "crest factor {smooth} vs {spiky} with one line"
+        );
+    }
+
+    #[test]
+    fn air_lifts_only_the_top_end() {
+        let mut state = 1;
+        let noise: Vec<f32> = (0..9600).map(|_| lcg_bipolar(&mut state)).collect();
+        let bands = |air_db: f32| {
+            let mut air = AirShelf::new(SR);
+            let out: Vec<f32> = noise.iter().map(|&x| air.process(x, x, air_db).0).collect();
+            let spectrum = analysis::spectrum(&out, SR, 256);
+            (
+                analysis::band_energy(&spectrum, SR, 0.0, 1000.0),
+                analysis::band_energy(&spectrum, SR, 8000.0, SR * 0.5),
+            )
+        };
+        let (flat_low, flat_high) = bands(0.0);
+        let mut last_high = flat_high;
+        for air_db in [3.0, 6.0, 9.0] {
+            let (low, high) = bands(air_db);
+            assert!(
+                high > last_high * 1.3,
+                "{air_db} dB: high band {high} vs {last_high}"
+            );
+            assert!(
+                (low / flat_low - 1.0).abs() < 0.05,
+                "{air_db} dB: low band {low} vs {flat_low}"
+            );
+            last_high = high;
+        }
+
+        // Flat at 0 dB
+        let mut air = AirShelf::new(SR);
+        assert!(noise.iter().all(|&x| air.process(x, x, 0.0).0 == x));
+    }
+
+    fn correlation(l: &[f32], r: &[f32]) -> f32 {
+        let dot = |a: &[f32], b: &[f32]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+        dot(l, r) / (dot(l, l) * dot(r, r)).sqrt().max(1e-12)
+    }
+
+    #[test]
+    fn bass_mono_narrows_only_below_the_crossover() {
+        // Left and right a quarter cycle apart: uncorrelated
+        let wide = |freq: f32, crossover: f32| {
+            let mut mono = BassMono::new(SR);
+            let (l, r): (Vec<f32>, Vec<f32>) = (0..9600)
+                .map(|i| {
+                    let w = 2.0 * std::f32::consts::PI * freq * i as f32 / SR;
+                    mono.process(w.sin(), w.cos(), crossover)
+                })
+                .unzip();
+            correlation(&l[4800..], &r[4800..])
+        };
+        assert!(wide(50.0, 0.0).abs() < 0.01);
+        // Mono material goes through untouched
+        let mut mono = BassMono::new(SR);
+        for i in 0..480 {
+            let x = (i as f32 * 0.05).sin();
+            let (l, r) = mono.process(x, x, 150.0);
+            assert!((l - x).abs() < 1e-6 && (r - x).abs() < 1e-6);
+        }
+        assert!(
+            wide(50.0, 150.0) > 0.95,
+            "low band correlation {}",
+            wide(50.0, 150.0)
+        );
+        assert!(
+            wide(5000.0, 150.0).abs() < 0.05,
+            "high band correlation {}",
+            wide(5000.0, 150.0)
+        );
+    }
+
+    #[test]
+    fn reflection_patterns_place_their_own_taps() {
+        let irs: Vec<_> = (0..ER_PATTERNS.len())
+            .map(|pattern| {
+                let max = ER_PATTERNS.len() as i32 - 1;
+                let range = IntRange::Linear { min: 0, max };
+                let drums = plugin(|p| {
+                    p.master.reverb_pattern = IntParam::new("ER", pattern as i32, range)
+                });
+                drums.reverb_impulse_response(12000)
+            })
+            .collect();
+
+        for (pattern, (l, r)) in ER_PATTERNS.iter().zip(&irs) {
+            for (ir, taps) in [(l, pattern.left), (r, pattern.right)] {
+                for &(time, _) in taps {
+                    let at = (time * SR) as usize;
+                    assert!(ir[at].abs() > 0.02, "{}: no tap at {at}", pattern.name);
+                }
+            }
+        }
+        for a in 0..irs.len() {
+            for b in a + 1..irs.len() {
+                let diff: f32 = irs[a]
+                    .0
+                    .iter()
+                    .zip(&irs[b].0)
+                    .map(|(x, y)| (x - y).abs())
+                    .sum();
+                assert!(diff > 0.5, "patterns {a} and {b} sound alike");
+            }
+        }
+    }
+
+    #[test]
+    fn unmapped_notes_are_counted() {
+        let mut drums = plugin(|_| {});
+        let diagnostics = drums.diagnostics();
+        let events = [
+            // Alone in a block, with the kit silent
+            note_on(10, 60, 1.0),
+            note_on(1000, 36, 1.0),
+            note_on(1000, 61, 1.0),
+            note_on(1200, 0, 0.5),
+            NoteEvent::NoteOff {
+                timing: 1300,
+                voice_id: None,
+                channel: 0,
+                note: 36,
+                velocity: 0.0,
+            },
+            NoteEvent::MidiCC {
+                timing: 1400,
+                channel: 0,
+                cc: 7,
+                value: 0.5,
+            },
+            note_on(2000, 38, 1.0),
+            note_on(3000, 127, 1.0),
+        ];
+        run(&mut drums, 4800, &events);
+
+        let seen = diagnostics.snapshot();
+        assert_eq!(seen.unmapped_notes, 4);
+        assert_eq!(seen.note_offs, 1);
+        assert_eq!(seen.other_events, 1);
+        assert_eq!(seen.dropped_hits, 0);
+    }
+
+    #[test]
+    fn stopping_the_host_cuts_the_tail_when_asked() {
+        let tail = |cut: bool| {
+            let mut drums = plugin(|p| {
+                dry(p);
+                p.master.reverb = float(0.8, 0.0, 1.0);
+                p.master.reverb_decay = float(3.0, 0.1, 4.0);
+                p.master.cut_tail_on_stop = BoolParam::new("Tail on Stop", cut);
+                p.snare.decay = float(50.0, 10.0, 2000.0);
+            });
+            let playing = TransportSnapshot {
+                playing: true,
+                ..stopped()
+            };
+            run_with(&mut drums, 4800, playing, &[note_on(0, 38, 1.0)]);
+            let [l, r] = run(&mut drums, 48000, &[]);
+            // The dry hit is long over by the last half second
+            rms(&l[24000..]) + rms(&r[24000..])
+        };
+        let (kept, cut) = (tail(false), tail(true));
+        assert!(kept > 1e-3);
+        assert!(
+            cut < 0.1 * kept,
+            "tail {cut} with the cut vs {kept} without"
+        );
+    }
+
+    #[test]
+    fn param_snapshot_round_trips_every_id() {
+        let drums = plugin(|_| {});
+        let gui = TestGui::default();
+        let original = drums.param_snapshot();
+        assert_eq!(original.len(), drums.params.param_map().len());
+
+        // Every param to the far end of its range, plus an id nobody has
+        let ptrs: HashMap<String, ParamPtr> = drums
+            .params
+            .param_map()
+            .into_iter()
+            .map(|(id, ptr, _)| (id, ptr))
+            .collect();
+        let mut moved: HashMap<String, f32> = ptrs
+            .iter()
+            .map(|(id, ptr)| unsafe {
+                let flipped = 1.0 - ptr.unmodulated_normalized_value();
+                (
+                    id.clone(),
+                    ptr.preview_plain(if flipped == 0.5 { 1.0 } else { flipped }),
+                )
+            })
+            .collect();
+        moved.insert("no_such_param".into(), 1.0);
+
+        let close = |a: &HashMap<String, f32>, b: &HashMap<String, f32>| {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(id, v)| (b[id] - v).abs() <= 1e-3 * v.abs().max(1.0))
+        };
+        assert_eq!(drums.apply_param_snapshot(&gui, &moved), original.len());
+        moved.remove("no_such_param");
+        let set = gui.take(&drums);
+        assert!(close(&set, &moved));
+        assert!(!close(&set, &original));
+
+        // And back again
+        assert_eq!(drums.apply_param_snapshot(&gui, &original), original.len());
+        assert!(close(&gui.take(&drums), &original));
+    }
+
+    #[test]
+    fn f64_mix_bus_keeps_detail_under_loud_slots() {
+        // Four loud slots and their inverted copies over a tone 120 dB down: after
+        // the sum only the tone should be left, plus whatever rounding adds
+        let levels = [0.9f32, 0.7, 1.3, 0.45];
+        let (mut err32, mut err64, mut power) = (0.0f64, 0.0f64, 0.0f64);
+        for i in 0..48000 {
+            let tone = 1e-6 * (i as f64 * 0.01).sin();
+            let (mut sum32, mut sum64) = (tone as f32, tone);
+            for sign in [1.0f32, -1.0] {
+                for (k, level) in levels.iter().enumerate() {
+                    let y = sign * (i as f32 * 0.013 * (k + 1) as f32).sin();
+                    // As the slot loop does it, and as it used to
+                    sum64 += y as f64 * *level as f64;
+                    sum32 += y * level;
+                }
+            }
+            err32 += (sum32 as f64 - tone).powi(2);
+            err64 += (sum64 - tone).powi(2);
+            power += tone * tone;
+        }
+        assert!(
+            err64 < 1e-12 * power,
+            "f64 residual {:e} of the tone",
+            err64 / power
+        );
+        assert!(
+            err32 > 1e-3 * power,
+            "f32 residual only {:e} of the tone",
+            err32 / power
+        );
+    }
+
+    #[test]
+    fn hat_pedal_sweeps_the_decay_from_closed_to_open() {
+        let mut drums = plugin(|p| {
+            p.hat_closed.humanize = float(0.0, 0.0, 1.0);
+            p.hat_open.humanize = float(0.0, 0.0, 1.0);
+        });
+        let (closed, open) = (
+            drums.params.hat_closed.decay.value(),
+            drums.params.hat_open.decay.value(),
+        );
+        let decays: Vec<f32> = (0..=8)
+            .map(|step| {
+                let openness = step as f32 / 8.0;
+                let pedal = NoteEvent::MidiCC {
+                    timing: 0,
+                    channel: 0,
+                    cc: HAT_PEDAL_CC,
+                    value: 1.0 - openness,
+                };
+                run(&mut drums, BLOCK, &[pedal, note_on(1, 42, 1.0)]);
+                drums.slots[HAT_CLOSED].decay_ms()
+            })
+            .collect();
+
+        assert!((decays[0] - closed).abs() < 0.5 && (decays[8] - open).abs() < 0.5);
+        // Evenly spaced on a log scale, so every pedal step lengthens it by the same ratio
+        let ratio = (open / closed).powf(1.0 / 8.0);
+        for w in decays.windows(2) {
+            assert!((w[1] / w[0] - ratio).abs() < 0.01, "{decays:?}");
+        }
+    }
+
+    #[test]
+    fn hat_pedal_keeps_the_closed_hat_flam() {
+        let mut drums = plugin(|p| {
+            p.hat_closed.flam = float(1.0, 0.0, 1.0);
+            p.hat_closed.decay = float(10.0, 10.0, 2000.0);
+            p.hat_open.decay = float(40.0, 10.0, 2000.0);
+            p.hat_closed.humanize = float(0.0, 0.0, 1.0);
+            p.hat_open.humanize = float(0.0, 0.0, 1.0);
+            dry(p);
+        });
+        let pedal = NoteEvent::MidiCC {
+            timing: 0,
+            channel: 0,
+            cc: HAT_PEDAL_CC,
+            value: 0.5,
+        };
+        let [l, _] = run(&mut drums, 24000, &[pedal, note_on(0, 46, 1.0)]);
+
+        // The open-hat note plays the closed voice, then its flam 40 ms later
+        assert_eq!(onsets(&l, 2.0), [0, 20]);
+        let peaks = windowed_peaks(&l, 2.0);
+        assert!(peaks[20] - peaks[19] < 0.5 * peaks[0]);
+        // The copy keeps the pedal's decay
+        assert!((drums.slots[HAT_CLOSED].decay_ms() - 20.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn empty_buffers_are_safe() {
+        let mut drums = plugin(|_| {});
+        let events = [note_on(0, 36, 1.0), note_on(0, 99, 1.0)];
+
+        // No samples: the block's events are drained and dropped
+        let mut queue = events.iter().copied();
+        let status = drums.render(&mut [], 0, stopped(), || queue.next());
+        assert!(matches!(status, ProcessStatus::Normal));
+        assert!(queue.next().is_none());
+        assert!(!drums.slots[0].is_active());
+        assert_eq!(drums.diagnostics().snapshot().unmapped_notes, 0);
+
+        // No channels: the block still plays, it just has nowhere to go
+        let mut queue = events.iter().copied();
+        let status = drums.render(&mut [], BLOCK, stopped(), || queue.next());
+        assert!(matches!(status, ProcessStatus::Normal));
+        assert!(drums.slots[0].is_active());
+        assert_eq!(drums.diagnostics().snapshot().unmapped_notes, 1);
+        let [l, _] = run(&mut drums, BLOCK, &[]);
+        assert!(peak(&l) > 0.01);
+    }
+
+    /// One note for each slot, in slot order.
+    const SLOT_NOTES: [u8; N_SLOTS] = [36, 38, 39, 42, 46, 45, 49, 51];
+
+    #[test]
+    fn tightness_scales_every_decay_alike() {
+        let decays = |tightness: f32| {
+            let mut drums = plugin(|p| {
+                for slot in slots_mut(p) {
+                    slot.humanize = float(0.0, 0.0, 1.0);
+                }
+                p.master.tightness = float(tightness, -1.0, 1.0);
+            });
+            run(&mut drums, BLOCK, &SLOT_NOTES.map(|n| note_on(0, n, 1.0)));
+            drums.slots.each_ref().map(|s| s.decay_ms())
+        };
+        let neutral = decays(0.0);
+        let defaults = DrumParams::default();
+        for (slot, &ms) in neutral.iter().enumerate() {
+            assert!((ms - defaults.slot(slot).decay.value()).abs() < 0.5);
+        }
+        for (tightness, scale) in [(0.5, 0.5f32.sqrt()), (1.0, 0.5), (-1.0, 2.0)] {
+            for (tight, plain) in decays(tightness).iter().zip(&neutral) {
+                assert!(
+                    (tight / plain - scale).abs() < 0.01,
+                    "tightness {tightness}: {tight} vs {plain} ms"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sum_guard_holds_a_full_kit_under_its_ceiling() {
+        // With the drive, comp and reverb out, the output is what the drive would see
+        let pre_drive = |guard: bool| {
+            let mut drums = plugin(|p| {
+                for slot in slots_mut(p) {
+                    slot.level = float(2.0, 0.0, 2.0);
+                    slot.pan = float(0.0, -1.0, 1.0);
+                }
+                for group in &mut p.groups {
+                    group.gain = float(2.0, 0.0, 2.0);
+                }
+                dry(p);
+                p.master.sum_guard = BoolParam::new("Sum Guard", guard);
+            });
+            let [l, r] = run(&mut drums, 4800, &SLOT_NOTES.map(|n| note_on(0, n, 1.0)));
+            peak(&l).max(peak(&r))
+        };
+        let (open, guarded) = (pre_drive(false), pre_drive(true));
+        assert!(
+            open > SUM_CEILING as f32,
+            "the unguarded sum only reached {open}"
+        );
+        assert!(guarded <= SUM_CEILING as f32, "{guarded}");
+        assert!(guarded > SUM_KNEE as f32);
+    }
+
+    #[test]
+    fn user_kit_recalls_what_was_saved() {
+        // A factory kit under some of the sound, plus a tweak of its own
+        let edm = FACTORY_KITS
+            .iter()
+            .position(|k| k.name == "EDM Punch")
+            .unwrap();
+        let mut saved = plugin(|p| {
+            let max = FACTORY_KITS.len() as i32 - 1;
+            p.master.kit = IntParam::new("Kit", edm as i32, IntRange::Linear { min: 0, max });
+            p.snare.flam = float(0.4, 0.0, 1.0);
+        });
+        saved.applied_kit = 0;
+        run(&mut saved, BLOCK, &[]);
+        assert!(saved.save_user_kit(2));
+        assert!(!saved.save_user_kit(N_USER_KITS));
+
+        // Another instance with the same state (as after a reload), still at the defaults
+        let recalled = plugin(|p| p.user_kits = saved.params.user_kits.clone());
+        let gui = TestGui::default();
+        assert!(!recalled.recall_user_kit(&gui, 0));
+        assert!(!recalled.recall_user_kit(&gui, N_USER_KITS));
+        assert!(gui.take(&recalled).is_empty());
+
+        assert!(recalled.recall_user_kit(&gui, 2));
+        let set = gui.take(&recalled);
+        // What was heard: the kit's macros where it covered them, the host's elsewhere
+        let heard = saved.heard_snapshot();
+        assert!(!heard.contains_key("kit"));
+        assert_eq!(heard["kick_lvl"], FACTORY_KITS[edm].slots[0][0]);
+        assert_eq!(heard["snare_flm"], 0.4);
+        assert_eq!(set.len(), heard.len());
+        for (id, value) in &set {
+            let want = heard[id];
+            assert!(
+                (value - want).abs() < 1e-4,
+                "{id}: recalled {value}, saved {want}"
+            );
+        }
+        // The recalled values replace the kit rather than sitting under it
+        assert!(recalled.release_kit.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn silent_kit_skips_blocks_only_after_the_tail() {
+        let mut drums = plugin(|p| p.master.reverb = float(0.5, 0.0, 1.0));
+        let diagnostics = drums.diagnostics();
+        let hold = (SILENCE_HOLD_SEC * SR) as usize;
+
+        let mut last_heard = 0;
+        let mut first_skip = None;
+        for block in 0..(10.0 * SR) as usize / BLOCK {
+            let events = if block == 0 {
+                vec![note_on(0, 36, 1.0)]
+            } else {
+                vec![]
+            };
+            let before = diagnostics.snapshot().silent_blocks;
+            let [l, r] = run(&mut drums, BLOCK, &events);
+            let start = block * BLOCK;
+            if diagnostics.snapshot().silent_blocks > before {
+                assert!(l.iter().chain(&r).all(|&x| x == 0.0));
+                first_skip.get_or_insert(start);
+            } else {
+                // Once skipping starts it carries on until something plays
+                assert!(
+                    first_skip.is_none(),
+                    "block at {start} rendered after skipping"
+                );
+                if let Some(i) = l
+                    .iter()
+                    .zip(&r)
+                    .rposition(|(l, r)| l.abs().max(r.abs()) > SILENCE)
+                {
+                    last_heard = start + i;
+                }
+            }
+        }
+        // The tail rang out before anything was skipped
+        let first_skip = first_skip.expect("a silent kit should skip blocks");
+        assert!(
+            last_heard > SR as usize / 2,
+            "tail lasted until {last_heard}"
+        );
+        assert!(first_skip >= last_heard + hold);
+        assert!(first_skip < last_heard + hold + 2 * BLOCK);
+
+        // The next hit plays straight away
+        let before = diagnostics.snapshot().silent_blocks;
+        let [l, _] = run(&mut drums, BLOCK, &[note_on(0, 36, 1.0)]);
+        assert!(peak(&l) > 0.1);
+        assert_eq!(diagnostics.snapshot().silent_blocks, before);
+    }
+
+    #[test]
+    fn offline_bounce_keeps_timing_tight_but_still_humanizes() {
+        // Eight kicks half a second apart: where each starts, and how loud it is
+        let hits = |live_only: bool, offline: bool| {
+            let mut drums = plugin(|p| {
+                dry(p);
+                p.master.humanize_timing = float(1.0, 0.0, 1.0);
+                p.master.timing_live_only = BoolParam::new("Test", live_only);
+                p.kick.humanize = float(1.0, 0.0, 1.0);
+                p.kick.decay = float(50.0, 10.0, 2000.0);
+            });
+            drums.offline = offline;
+            let spacing = 24000;
+            let events: Vec<_> = (0..8)
+                .map(|i| note_on(i * spacing as u32, 36, 0.8))
+                .collect();
+            let [l, _] = run(&mut drums, 8 * spacing, &events);
+            l.chunks(spacing)
+                .map(|hit| (hit.iter().position(|x| x.abs() > 1e-3).unwrap(), peak(hit)))
+                .collect::<Vec<_>>()
+        };
+        let late = |hits: &[(usize, f32)]| {
+            let on_grid = hits.iter().map(|(at, _)| *at).min().unwrap();
+            hits.iter().filter(|(at, _)| *at > on_grid).count()
+        };
+        let spread = |hits: &[(usize, f32)]| {
+            let peaks = hits.iter().map(|(_, p)| *p);
+            peaks.clone().fold(0.0, f32::max) / peaks.fold(f32::MAX, f32::min)
+        };
+
+        // Bouncing with the gate on: every hit on the grid, levels still loose
+        let bounced = hits(true, true);
+        assert_eq!(late(&bounced), 0);
+        assert!(spread(&bounced) > 1.05, "level spread {}", spread(&bounced));
+
+        // Live, or with the gate off (the default), timing humanize still applies
+        assert!(late(&hits(true, false)) > 2);
+        assert!(late(&hits(false, true)) > 2);
+    }
+
+    #[test]
+    fn invert_phase_negates_the_output_exactly() {
+        let render = |invert: bool| {
+            // The full master chain, reverb and all
+            let mut drums = plugin(|p| p.master.invert_phase = BoolParam::new("Test", invert));
+            let events = [
+                note_on(0, 36, 1.0),
+                note_on(3000, 38, 0.7),
+                note_on(6000, 42, 0.9),
+            ];
+            run(&mut drums, 24000, &events)
+        };
+        let (normal, inverted) = (render(false), render(true));
+        assert!(peak(&normal[0]) > 0.1);
+        for (a, b) in normal.iter().zip(&inverted) {
+            assert!(a.iter().zip(b).all(|(a, b)| *b == -*a));
+        }
+    }
+
+    #[test]
+    fn reverb_throw_ramps_the_mix_smoothly() {
+        let render = |throw: bool| {
+            let mut drums = plugin(|p| {
+                dry(p);
+                // As declared, with its smoother
+                p.master.reverb = float(0.0, 0.0, 1.0).with_smoother(SmoothingStyle::Linear(20.0));
+            });
+            if throw {
+                // The host automates the reverb up as the hit lands
+                drums.params.master.reverb.smoothed.set_target(SR, 1.0);
+            }
+            let [l, _] = run(&mut drums, 1200, &[note_on(0, 36, 0.3)]);
+            l
+        };
+        let (plain, thrown) = (render(false), render(true));
+
+        // The room's first echo is 30 ms out, so until then the output is the dry
+        // signal under the mix's dry gain, 1 - 0.6 × amount
+        let amounts: Vec<(usize, f32)> = plain
+            .iter()
+            .zip(&thrown)
+            .enumerate()
+            .filter(|(_, (p, _))| p.abs() > 1e-3)
+            .map(|(i, (p, t))| (i, (1.0 - t / p) / 0.6))
+            .collect();
+        let ramp = (0.02 * SR) as usize;
+        for &(i, amount) in &amounts {
+            let expected = ((i + 1) as f32 / ramp as f32).min(1.0);
+            assert!(
+                (amount - expected).abs() < 0.01,
+                "sample {i}: amount {amount}, expected {expected}"
+            );
+        }
+        assert!(amounts.iter().any(|&(i, _)| i > ramp));
+    }
+
+    #[test]
+    fn reflection_taps_stay_distinct_at_low_rates() {
+        for sr in [8000.0, 1000.0, 100.0] {
+            for (i, pattern) in ER_PATTERNS.iter().enumerate() {
+                let mut reverb = SimpleReverb::new(sr);
+                reverb.set_pattern(i);
+                let len = reverb.er_buf.len();
+                for (taps, times) in [
+                    (&reverb.taps_l, pattern.left),
+                    (&reverb.taps_r, pattern.right),
+                ] {
+                    let delays = &taps.delays[..taps.count];
+                    assert_eq!(delays.len(), times.len().min(MAX_ER_TAPS));
+                    assert!(
+                        delays.windows(2).all(|w| w[0] < w[1]) && delays.iter().all(|&d| d < len),
+                        "{} at {sr} Hz: taps {delays:?} in {len}",
+                        pattern.name
+                    );
+                }
+            }
+        }
+
+        // At 8 kHz each tap ahead of the late tail is its own echo
+        let first_late = (FDN_DELAYS[0] * 8000.0) as usize;
+        for (i, pattern) in ER_PATTERNS.iter().enumerate() {
+            let mut reverb = SimpleReverb::new(8000.0);
+            reverb.set_pattern(i);
+            let echoes = (0..first_late)
+                .filter(|&n| {
+                    let x = if n == 0 { 1.0 } else { 0.0 };
+                    reverb.process(0.0, 0.0, x, x, 1.0).0 != 0.0
+                })
+                .count();
+            let early = pattern
+                .left
+                .iter()
+                .filter(|(time, _)| ((time * 8000.0) as usize) < first_late)
+                .count();
+            assert_eq!(echoes, early, "{}", pattern.name);
+        }
+    }
+
+    #[test]
+    fn dual_mono_comp_spares_the_quiet_side() {
+        // A loud hit panned hard left over a quiet right channel
+        let gains = |linked: bool| {
+            let mut comp = SimpleComp::new(SR);
+            comp.set_linked(linked);
+            (0..4800)
+                .map(|i| {
+                    let t = i as f64 / SR as f64;
+                    let l = (-t / 0.05).exp() * (2.0 * std::f64::consts::PI * 80.0 * t).sin();
+                    comp.gains(l, 0.01 * l, 1.0, false, 1.0)
+                })
+                .fold((1.0f64, 1.0f64), |(gl, gr), (l, r)| (gl.min(l), gr.min(r)))
+        };
+        let ((linked_l, linked_r), (dual_l, dual_r)) = (gains(true), gains(false));
+        assert!(linked_l < 0.7 && (dual_l - linked_l).abs() < 1e-9);
+        assert!((linked_r - linked_l).abs() < 1e-9);
+        // The right side never crosses the threshold on its own
+        assert_eq!(dual_r, 1.0);
+    }
+
+    #[test]
+    fn reverb_impulse_response_starts_silent_and_decays() {
+        let drums = plugin(|p| p.master.reverb_decay = float(1.0, 0.1, 4.0));
+        let (l, r) = drums.reverb_impulse_response((2.0 * SR) as usize);
+        let pattern = &ER_PATTERNS[drums.params.master.reverb_pattern.value() as usize];
+
+        for (ir, taps) in [(&l, pattern.left), (&r, pattern.right)] {
+            // Nothing before the first reflection or the tail's first echo, then every tap
+            let first = (taps[0].0.min(FDN_DELAYS[0]) * SR) as usize;
+            assert!(ir[..first].iter().all(|&x| x == 0.0));
+            for &(time, gain) in taps {
+                assert!(ir[(time * SR) as usize].abs() >= gain * 0.5);
+            }
+
+            // Each quarter second quieter than the last
+            let quarters: Vec<f32> = ir.chunks(SR as usize / 4).map(rms).collect();
+            assert!(quarters.windows(2).all(|w| w[1] < w[0]), "{quarters:?}");
+            assert!(quarters[quarters.len() - 1] < 0.01 * quarters[0]);
+        }
+    }
+
+    #[test]
+    fn center_compensation_trims_a_centred_kit() {
+        // All eight slots centred and hit together, quietly enough to stay linear
+        let render = |compensate: bool| {
+            let mut drums = plugin(|p| {
+                dry(p);
+                p.master.center_comp = BoolParam::new("Test", compensate);
+                for slot in slots_mut(p) {
+                    slot.pan = float(0.0, -1.0, 1.0);
+                }
+            });
+            let events: Vec<_> = SLOT_NOTES.iter().map(|&n| note_on(0, n, 0.2)).collect();
+            let [l, r] = run(&mut drums, 9600, &events);
+            let mid: Vec<f32> = l.iter().zip(&r).map(|(l, r)| (l + r) * 0.5).collect();
+            let side: Vec<f32> = l.iter().zip(&r).map(|(l, r)| (l - r) * 0.5).collect();
+            (rms(&mid), rms(&side))
+        };
+        let ((mid, side), (trimmed_mid, trimmed_side)) = (render(false), render(true));
+        assert!(
+            (trimmed_mid / mid - CENTER_TRIM as f32).abs() < 0.01,
+            "mid {trimmed_mid} compensated vs {mid}"
+        );
+        assert!(side < 1e-3 * mid && trimmed_side < 1e-3 * mid);
+    }
+
+    #[test]
+    fn same_frame_events_ignore_host_order() {
+        let pedal = NoteEvent::MidiCC {
+            timing: 100,
+            channel: 0,
+            cc: HAT_PEDAL_CC,
+            value: 0.6,
+        };
+        let frame = [
+            note_on(100, 42, 0.8),
+            note_on(100, 38, 0.4),
+            pedal,
+            note_on(100, 36, 1.0),
+            note_on(100, 38, 0.9),
+            note_on(100, 46, 0.7),
+        ];
+        let render = |events: &[PluginNoteEvent<Drumini>]| run(&mut plugin(|_| {}), 9600, events);
+
+        // Pedal first, then one hit per slot in slot order, the louder snare winning
+        let expected = render(&[
+            pedal,
+            note_on(100, 36, 1.0),
+            note_on(100, 38, 0.9),
+            note_on(100, 42, 0.8),
+            note_on(100, 46, 0.7),
+        ]);
+        assert!(peak(&expected[0]) > 0.1);
+        let mut order = frame.to_vec();
+        assert_eq!(render(&order), expected);
+        order.reverse();
+        assert_eq!(render(&order), expected);
+        order.rotate_left(2);
+        assert_eq!(render(&order), expected);
+    }
+
+    #[test]
+    fn transistor_drive_adds_even_harmonics_and_no_offset() {
+        // A hot 1 kHz tone, fully driven: even harmonics against the fundamental, and
+        // the output's mean once the DC blocker has settled
+        let drive = |model: usize| {
+            let mut drive = MasterDrive::new(SR);
+            let out: Vec<f32> = (0..48000)
+                .map(|i| {
+                    let x =
+                        0.8 * (2.0 * std::f64::consts::PI * 1000.0 * i as f64 / SR as f64).sin();
+                    drive.process(x, x, 1.0, model).0 as f32
+                })
+                .skip(24000)
+                .collect();
+            let even: f32 = [2000.0, 4000.0]
+                .into_iter()
+                .map(|f| analysis::goertzel(&out, SR, f))
+                .sum();
+            let mean = out.iter().sum::<f32>() / out.len() as f32;
+            (even / analysis::goertzel(&out, SR, 1000.0), mean)
+        };
+        let ((tanh_even, tanh_mean), (transistor_even, transistor_mean)) = (drive(0), drive(2));
+        assert!(tanh_even < 1e-3, "tanh even harmonics {tanh_even}");
+        assert!(
+            transistor_even > 0.02,
+            "transistor even harmonics {transistor_even}"
+        );
+        assert!(
+            tanh_mean.abs() < 1e-4 && transistor_mean.abs() < 1e-3,
+            "DC {transistor_mean}"
+        );
+    }
+
+    #[test]
+    fn longest_tail_converges_under_steady_input() {
+        let mut state = 1;
+        let noise: Vec<f64> = (0..(8.0 * SR) as usize)
+            .map(|_| lcg_bipolar(&mut state) as f64 * 0.5)
+            .collect();
+        // RMS of each second of the tail under a steady feed
+        let seconds = |fdn: &mut Fdn| {
+            let out: Vec<f32> = noise
+                .iter()
+                .map(|&x| fdn.process(x, false).0 as f32)
+                .collect();
+            out.chunks(SR as usize)
+                .map(|s| (rms(s), peak(s)))
+                .collect::<Vec<_>>()
+        };
+
+        // The longest decay there is: the loop gain is held under unity
+        let mut fdn = Fdn::new(SR, usize::MAX).unwrap();
+        fdn.set_tail(FDN_MAX_LINES, 1000.0);
+        assert!(fdn.gains.iter().all(|&g| g <= FDN_MAX_GAIN as f64));
+        let levels = seconds(&mut fdn);
+        let (last, before) = (levels[7].0, levels[6].0);
+        assert!((last / before - 1.0).abs() < 0.05, "tail rms {levels:?}");
+
+        // Past unity the watchdog catches it
+        fdn.gains = [1.2; FDN_MAX_LINES];
+        let levels = seconds(&mut fdn);
+        assert!(
+            levels.iter().all(|&(_, p)| p < 2.0 * FDN_CEILING),
+            "tail {levels:?}"
+        );
+    }
+
+    #[test]
+    fn group_gain_turns_down_only_its_slots() {
+        // Each slot alone, with the hats routed to a second group at half gain or not
+        let render = |note: u8, grouped: bool| {
+            let mut drums = plugin(|p| {
+                dry(p);
+                if grouped {
+                    p.hat_closed.group =
+                        IntParam::new("Test", 1, IntRange::Linear { min: 0, max: 1 });
+                    p.hat_open.group =
+                        IntParam::new("Test", 1, IntRange::Linear { min: 0, max: 1 });
+                    p.groups[1].gain = float(0.5, 0.0, 2.0);
+                }
+            });
+            run(&mut drums, 9600, &[note_on(0, note, 0.5)])[0].clone()
+        };
+        for (slot, &note) in SLOT_NOTES.iter().enumerate() {
+            let (plain, grouped) = (render(note, false), render(note, true));
+            assert!(peak(&plain) > 0.01);
+            if matches!(slot, HAT_CLOSED | HAT_OPEN) {
+                assert!(
+                    (rms(&grouped) / rms(&plain) - 0.5).abs() < 0.01,
+                    "slot {slot}"
+                );
+            } else {
+                assert_eq!(grouped, plain, "slot {slot}");
+            }
+        }
+    }
+
+    #[test]
+    fn default_kit_stays_under_full_scale() {
+        // Two bars of sixteenths at 120 BPM, every slot on the downbeats and the
+        // hats, kick and snare busy in between, all at full velocity
+        let mut drums = plugin(|_| {});
+        let sixteenth = (SR * 0.125) as u32;
+        let mut events = Vec::new();
+        for step in 0..32 {
+            let at = step * sixteenth;
+            if step % 16 == 0 {
+                events.extend(SLOT_NOTES.iter().map(|&n| note_on(at, n, 1.0)));
+                continue;
+            }
+            events.push(note_on(at, if step % 4 == 2 { 46 } else { 42 }, 1.0));
+            if step % 4 == 0 {
+                events.push(note_on(at, if step % 8 == 0 { 36 } else { 38 }, 1.0));
+            }
+        }
+        let [l, r] = run(&mut drums, 32 * sixteenth as usize + SR as usize, &events);
+        let loudest = peak(&l).max(peak(&r));
+        assert!(loudest > 0.3 && loudest < 1.0, "peak {loudest}");
+    }
+
+    #[test]
+    fn stereoize_widens_a_centred_kit_and_keeps_its_mono_sum() {
+        let render = |width: f32| {
+            let mut drums = plugin(|p| {
+                dry(p);
+                p.master.stereoize = float(width, 0.0, 1.0);
+                for slot in slots_mut(p) {
+                    slot.pan = float(0.0, -1.0, 1.0);
+                }
+            });
+            let events = [
+                note_on(0, 36, 0.5),
+                note_on(4800, 38, 0.5),
+                note_on(9600, 42, 0.5),
+            ];
+            let [l, r] = run(&mut drums, 19200, &events);
+            let mono: Vec<f32> = l.iter().zip(&r).map(|(l, r)| l + r).collect();
+            let side: Vec<f32> = l.iter().zip(&r).map(|(l, r)| l - r).collect();
+            (mono, rms(&side))
+        };
+        let ((mono, side), (wide_mono, wide_side)) = (render(0.0), render(1.0));
+        assert!(side < 1e-6 * rms(&mono));
+        assert!(
+            wide_side > 0.2 * rms(&mono),
+            "side {wide_side} against mono {}",
+            rms(&mono)
+        );
+        assert!(
+            mono.iter()
+                .zip(&wide_mono)
+                .all(|(a, b)| (a - b).abs() < 1e-5)
+        );
+    }
+
+    #[test]
+    fn transport_snapshot_drives_sync_for_the_whole_block() {
+        let transport = TransportSnapshot {
+            playing: true,
+            tempo: Some(120.0),
+            pos_beats: Some(4.25),
+            sample_rate: SR,
+        };
+        assert_eq!(transport.samples_per_beat(), Some(24000.0));
+        assert_eq!(transport.beats_at(12000), Some(4.75));
+        let unknown = TransportSnapshot {
+            tempo: None,
+            ..transport
+        };
+        assert_eq!(unknown.samples_per_beat(), None);
+        assert_eq!(unknown.beats_at(12000), None);
+
+        // A one-beat LFO locks to the song position and steps at the snapshot's tempo
+        let rate = LFO_BEATS.iter().position(|&b| b == 1.0).unwrap();
+        let mut drums = plugin(|p| {
+            let max = LFO_BEATS.len() as i32 - 1;
+            p.master.lfo_rate =
+                IntParam::new("Test", rate as i32, IntRange::Linear { min: 0, max });
+            p.master.lfo_depth = float(0.5, 0.0, 1.0);
+        });
+        run_with(&mut drums, BLOCK, transport, &[note_on(0, 36, 1.0)]);
+        assert_eq!(drums.transport, transport);
+        let expected = 0.25 + BLOCK as f64 / 24000.0;
+        assert!(
+            (drums.lfo_phase - expected).abs() < 1e-9,
+            "phase {}",
+            drums.lfo_phase
+        );
+
+        // Stopped, it free-runs from where it was at the same tempo
+        let stopped = TransportSnapshot {
+            playing: false,
+            ..transport
+        };
+        run_with(&mut drums, BLOCK, stopped, &[]);
+        let expected = expected + BLOCK as f64 / 24000.0;
+        assert!(
+            (drums.lfo_phase - expected).abs() < 1e-9,
+            "phase {}",
+            drums.lfo_phase
+        );
+    }
+
+    #[test]
+    fn trigger_slot_plays_without_midi() {
+        let mut drums = plugin(|_| {});
+        assert!(!drums.trigger_slot(N_SLOTS, 1.0));
+        let [silent, _] = run(&mut drums, BLOCK, &[]);
+        assert_eq!(peak(&silent), 0.0);
+
+        // Queued now, heard from the first sample of the next block
+        assert!(drums.trigger_slot(0, 1.0));
+        let [triggered, _] = run(&mut drums, 4800, &[]);
+        let [played, _] = run(&mut plugin(|_| {}), 4800, &[note_on(0, 36, 1.0)]);
+        assert!(peak(&triggered) > 0.1);
+        assert_eq!(triggered, played);
+    }
+
+    #[test]
+    fn ab_swap_recalls_each_side() {
+        let mut drums = plugin(|_| {});
+        let gui = TestGui::default();
+        let a = drums.heard_snapshot();
+        drums.ab_store();
+
+        // B starts as a copy of A, so switching to it changes nothing
+        assert!(drums.ab_recall(&gui, 1));
+        assert!(gui.take(&drums).is_empty());
+        assert!(!drums.ab_recall(&gui, 2));
+
+        // Edits on B, as the host would apply them
+        let params = Arc::get_mut(&mut drums.params).unwrap();
+        params.kick.level = float(1.5, 0.0, 2.0);
+        params.snare.flam = float(0.4, 0.0, 1.0);
+        let b = drums.heard_snapshot();
+
+        // Back to A: every value as stored, set through the host
+        drums.ab_swap(&gui);
+        let set = gui.take(&drums);
+        assert_eq!(set.len(), a.len());
+        for (id, value) in &set {
+            assert!(
+                (value - a[id]).abs() < 1e-4,
+                "{id}: {value} vs A's {}",
+                a[id]
+            );
+        }
+        // The host applies them
+        let params = Arc::get_mut(&mut drums.params).unwrap();
+        params.kick.level = float(a["kick_lvl"], 0.0, 2.0);
+        params.snare.flam = float(a["snare_flm"], 0.0, 1.0);
+
+        // And over to B again: its edits come back
+        drums.ab_swap(&gui);
+        let set = gui.take(&drums);
+        assert_eq!(set.len(), b.len());
+        assert!((set["kick_lvl"] - 1.5).abs() < 1e-4);
+        assert!((set["snare_flm"] - 0.4).abs() < 1e-4);
+        for (id, value) in &set {
+            assert!(
+                (value - b[id]).abs() < 1e-4,
+                "{id}: {value} vs B's {}",
+                b[id]
+            );
+        }
+        // Leaving A kept it as it was
+        let ab = drums.params.ab_compare.read().unwrap();
+        assert_eq!(ab.0, 1);
+        assert_eq!(ab.1[0].as_ref(), Some(&a));
+    }
+
+    #[test]
+    fn slot_pan_follows_the_master_law_unless_overridden() {
+        // The kick panned right under the master law; the snare the same, but with
+        // Equal Power as its own law
+        let render = |note: u8, master_law: i32| {
+            let laws = |max| IntRange::Linear { min: 0, max };
+            let mut drums = plugin(|p| {
+                dry(p);
+                p.master.pan_law =
+                    IntParam::new("Test", master_law, laws(PAN_LAWS.len() as i32 - 1));
+                p.kick.pan = float(0.5, -1.0, 1.0);
+                p.snare.pan = float(0.5, -1.0, 1.0);
+                p.snare.pan_law = IntParam::new("Test", 1, laws(PAN_LAWS.len() as i32));
+            });
+            run(&mut drums, 4800, &[note_on(0, note, 0.5)])
+        };
+        let balance = |[l, r]: &[Vec<f32>; 2]| rms(l) / rms(r);
+        let ratio = |law| pan_to_gains(0.5, law).0 / pan_to_gains(0.5, law).1;
+        for (law, name) in PAN_LAWS.iter().enumerate() {
+            let kick = render(36, law as i32);
+            assert!((balance(&kick) - ratio(law)).abs() < 1e-3, "{name}");
+            let snare = render(38, law as i32);
+            assert!((balance(&snare) - ratio(0)).abs() < 1e-3, "{name}");
+        }
+        // The laws really differ where the kick sits
+        assert!((ratio(2) - ratio(0)).abs() > 0.05);
+    }
+
+    #[test]
+    fn sc_listen_outputs_what_the_compressor_hears() {
+        // A hard-squashed, driven, wet kit: listening to the sidechain hears none of it
+        let hits = [note_on(0, 36, 1.0), note_on(2400, 38, 0.8)];
+        let mut squashed = plugin(|p| {
+            p.master.comp = float(1.0, 0.0, 1.0);
+            p.master.drive = float(0.8, 0.0, 1.0);
+            p.master.reverb = float(0.6, 0.0, 1.0);
+            p.master.sc_listen = BoolParam::new("Test", true);
+        });
+        let listened = run(&mut squashed, 9600, &hits);
+        let mut plain = plugin(dry);
+        let expected = run(&mut plain, 9600, &hits);
+        for ch in 0..2 {
+            for (a, b) in listened[ch].iter().zip(&expected[ch]) {
+                assert!((a - b).abs() < 1e-5, "{a} vs {b}");
+            }
+        }
+        assert!(peak(&listened[0]) > 0.1);
+
+        // With it off the processing is heard again
+        let mut heard = plugin(|p| {
+            p.master.comp = float(1.0, 0.0, 1.0);
+            p.master.drive = float(0.8, 0.0, 1.0);
+            p.master.reverb = float(0.6, 0.0, 1.0);
+        });
+        let processed = run(&mut heard, 9600, &hits);
+        let diff = processed[0]
+            .iter()
+            .zip(&expected[0])
+            .map(|(a, b)| (a - b).abs());
+        assert!(diff.fold(0.0, f32::max) > 0.05);
+    }
+
+    #[test]
+    fn channels_past_stereo_are_left_silent() {
+        let mut drums = plugin(|_| {});
+        // Host garbage in every channel, and a hit to render over it
+        let mut channels = [[0.7f32; BLOCK]; 4];
+        let mut hits = [note_on(0, 36, 1.0)].into_iter();
+        let mut outputs: Vec<&mut [f32]> = channels.iter_mut().map(|c| &mut c[..]).collect();
+        drums.render(&mut outputs, BLOCK, stopped(), || hits.next());
+
+        // The stereo pair carries the kick
+        assert!(channels[0].iter().any(|&v| v != 0.7));
+        assert!(channels[1].iter().any(|&v| v != 0.7));
+        assert!(peak(&channels[0]) > 0.05);
+        // Channels 3 and 4 are cleared
+        assert!(channels[2].iter().chain(&channels[3]).all(|&v| v == 0.0));
+    }
+
+    #[test]
+    fn comp_attack_sets_how_fast_the_reduction_ramps_in() {
+        // Gain reduction (dB) over a full-scale step, sampled each ms
+        let ramp = |attack_ms: f32| {
+            let mut comp = SimpleComp::new(SR);
+            comp.set_times(attack_ms, COMP_RELEASE_MS);
+            (0..SR as usize / 2)
+                .map(|_| -20.0 * comp.gains(1.0, 1.0, 1.0, false, 1.0).0.log10())
+                .step_by(SR as usize / 1000)
+                .collect::<Vec<_>>()
+        };
+        let slow = ramp(50.0);
+        let full = slow[400];
+        assert!(full > 6.0);
+        // A fifth of the way into the attack less than half of it has landed;
+        // three attack times in, nearly all of it
+        assert!(slow[10] < 0.5 * full, "{} of {full} dB", slow[10]);
+        assert!(slow[150] > 0.9 * full, "{} of {full} dB", slow[150]);
+        assert!(slow.windows(2).all(|w| w[1] >= w[0] - 1e-6));
+
+        // The default attack is nearly there by 20 ms
+        let fast = ramp(COMP_ATTACK_MS);
+        assert!(fast[20] > 0.9 * full);
+        assert!((fast[400] - full).abs() < 0.01);
+    }
+
+    #[test]
+    fn gated_send_feeds_the_reverb_only_the_attack() {
+        // A long snare: the reverb's share of the output, late against early
+        let wet = |gated: bool| {
+            let edit = |p: &mut DrumParams, reverb: f32| {
+                dry(p);
+                p.master.reverb = float(reverb, 0.0, 1.0);
+                p.snare.decay = float(800.0, 10.0, 2000.0);
+                p.snare.gated_send = BoolParam::new("Test", gated);
+            };
+            let hits = [note_on(0, 38, 1.0)];
+            let mut with = plugin(|p| edit(p, 1.0));
+            let mut without = plugin(|p| edit(p, 0.0));
+            let (out, direct) = (
+                run(&mut with, 48000, &hits),
+                run(&mut without, 48000, &hits),
+            );
+            // At full amount the dry path sits at 0.4
+            let wet: Vec<f32> = out[0]
+                .iter()
+                .zip(&direct[0])
+                .map(|(o, d)| o - 0.4 * d)
+                .collect();
+            (rms(&wet[..4800]), rms(&wet[19200..38400]))
+        };
+        let ((full_early, full_late), (gated_early, gated_late)) = (wet(false), wet(true));
+        // The attack still reaches the reverb...
+        assert!(
+            gated_early > 0.2 * full_early,
+            "{gated_early} vs {full_early}"
+        );
+        // ...but the body of the hit, ringing on, no longer feeds it
+        assert!(gated_late < 0.01 * full_late, "{gated_late} vs {full_late}");
+    }
+
+    #[test]
+    fn playing_flag_follows_the_tail_down_to_silence() {
+        let mut drums = plugin(|p| p.master.reverb = float(0.5, 0.0, 1.0));
+        let flag = drums.playing_flag();
+        assert!(!drums.is_playing_any());
+
+        // Set by the hit, and held while it and the reverb ring
+        let mut playing = vec![];
+        let mut last_heard = 0;
+        for block in 0..(5.0 * SR) as usize / BLOCK {
+            let events = if block == 0 {
+                vec![note_on(0, 38, 1.0)]
+            } else {
+                vec![]
+            };
+            let [l, r] = run(&mut drums, BLOCK, &events);
+            let now = flag.load(Ordering::Relaxed);
+            assert_eq!(now, drums.is_playing_any());
+            // Never idle while something can still be heard
+            if l.iter().chain(&r).any(|x| x.abs() > SILENCE) {
+                assert!(now, "idle in audible block {block}");
+                last_heard = block;
+            }
+            playing.push(now);
+        }
+        assert!(playing[..(0.2 * SR) as usize / BLOCK].iter().all(|&p| p));
+        // Down once the tail has gone, and for good
+        assert!(last_heard + 1 < playing.len());
+        assert!(!playing[last_heard + 1..].iter().any(|&p| p));
+    }
+
+    #[test]
+    fn mono_monitor_folds_both_channels_to_the_sum() {
+        // A wide kit: panned slots and the reverb
+        let render = |mono: bool| {
+            let mut drums = plugin(|p| {
+                p.snare.pan = float(-0.7, -1.0, 1.0);
+                p.hat_closed.pan = float(0.8, -1.0, 1.0);
+                p.master.mono_monitor = BoolParam::new("Test", mono);
+            });
+            let hits = [note_on(0, 38, 1.0), note_on(1200, 42, 0.9)];
+            run(&mut drums, 9600, &hits)
+        };
+        let [sl, sr] = render(false);
+        assert!(sl.iter().zip(&sr).any(|(l, r)| (l - r).abs() > 0.01));
+
+        let [ml, mr] = render(true);
+        assert_eq!(ml, mr);
+        // Averaged, so a centred signal keeps its level
+        for ((m, l), r) in ml.iter().zip(&sl).zip(&sr) {
+            assert!((m - (l + r) * 0.5).abs() < 1e-6, "{m} vs {l} + {r}");
+        }
+    }
+
+    #[test]
+    fn pre_verb_high_pass_keeps_the_lows_out_of_the_tail() {
+        let bands = |hp: f32| {
+            let drums = plugin(|p| {
+                p.master.reverb_hp = float(hp, PRE_VERB_HP_MIN, 1000.0);
+            });
+            let (l, _) = drums.reverb_impulse_response(SR as usize);
+            let spectrum = analysis::spectrum(&l, SR, 512);
+            (
+                analysis::band_energy(&spectrum, SR, 0.0, 120.0),
+                analysis::band_energy(&spectrum, SR, 2000.0, 8000.0),
+            )
+        };
+        let ((full_low, full_high), (low, high)) = (bands(PRE_VERB_HP_MIN), bands(500.0));
+        assert!(low < 0.02 * full_low, "lows {low} vs {full_low}");
+        // Well above the cutoff the tail is untouched
+        assert!(high > 0.8 * full_high, "highs {high} vs {full_high}");
+    }
+
+    #[test]
+    fn tail_length_covers_a_max_decay_hit_and_the_reverb() {
+        let mut drums = plugin(|p| {
+            p.tom.decay = float(2000.0, 10.0, 2000.0);
+            p.master.reverb = float(0.5, 0.0, 1.0);
+            p.master.reverb_decay = float(2.0, 0.1, 4.0);
+        });
+        let tail = drums.tail_samples() as usize;
+
+        // Play the longest hit and listen until it has fully gone
+        let [l, r] = run(&mut drums, 30 * SR as usize, &[note_on(0, 45, 1.0)]);
+        let heard = l
+            .iter()
+            .zip(&r)
+            .rposition(|(l, r)| l.abs().max(r.abs()) > SILENCE)
+            .unwrap();
+        assert!(heard > 10 * SR as usize, "rang for {heard}");
+        assert!(tail >= heard, "tail {tail} vs heard until {heard}");
+    }
+
+    #[test]
+    fn mono_sum_law_holds_the_fold_down_level_across_the_field() {
+        // The kick's mono fold-down level at each pan position
+        let mono_levels = |law: i32| {
+            [-1.0, -0.5, 0.0, 0.5, 1.0].map(|pan| {
+                let mut drums = plugin(|p| {
+                    dry(p);
+                    p.master.pan_law =
+                        IntParam::new("Test", law, IntRange::Linear { min: 0, max: 2 });
+                    p.kick.pan = float(pan, -1.0, 1.0);
+                });
+                let [l, r] = run(&mut drums, 4800, &[note_on(0, 36, 0.5)]);
+                let sum: Vec<f32> = l.iter().zip(&r).map(|(l, r)| l + r).collect();
+                rms(&sum)
+            })
+        };
+        let mono_sum = mono_levels(2);
+        for level in &mono_sum {
+            assert!((level / mono_sum[2] - 1.0).abs() < 1e-3, "{mono_sum:?}");
+        }
+        // Equal power bulges in the middle of a mono fold-down
+        let equal_power = mono_levels(0);
+        assert!(equal_power[2] / equal_power[0] > 1.3, "{equal_power:?}");
+    }
+
+    #[test]
+    fn tom_notes_play_three_ascending_pitches() {
+        // The strongest partial of the tom's ringing body, to the nearest Hz
+        let fundamental = |note: u8| {
+            let mut drums = plugin(dry);
+            let [l, _] = run(&mut drums, 9600, &[note_on(0, note, 0.8)]);
+            let body = &l[2400..];
+            (40..400)
+                .map(|f| (f, analysis::goertzel(body, SR, f as f32)))
+                .fold(
+                    (0, 0.0),
+                    |best, (f, m)| if m > best.1 { (f, m) } else { best },
+                )
+                .0 as f32
+        };
+        let [floor, low, low_mid] = [43, 45, 47].map(fundamental);
+        assert!(floor < low && low < low_mid, "{floor} < {low} < {low_mid}");
+        // A fourth either side of the middle tom
+        let fourth = 2.0f32.powf(5.0 / 12.0);
+        assert!((low / floor / fourth - 1.0).abs() < 0.03, "{floor} {low}");
+        assert!(
+            (low_mid / low / fourth - 1.0).abs() < 0.03,
+            "{low} {low_mid}"
+        );
+    }
+
+    #[test]
+    fn eco_takes_the_cheap_paths_whatever_the_params_ask() {
+        let int = |value, max| IntParam::new("Test", value, IntRange::Linear { min: 0, max });
+        let render = |edit: &dyn Fn(&mut DrumParams)| {
+            let mut drums = plugin(|p| {
+                p.master.drive = float(0.7, 0.0, 1.0);
+                p.master.reverb = float(0.5, 0.0, 1.0);
+                edit(p);
+            });
+            let hits = [note_on(0, 36, 1.0), note_on(2400, 38, 0.9)];
+            run(&mut drums, 24000, &hits)
+        };
+        // The costly settings: a modelled drive and the densest tail
+        let costly = |p: &mut DrumParams| {
+            p.master.drive_model =
+                int(DRIVE_MODELS.len() as i32 - 1, DRIVE_MODELS.len() as i32 - 1);
+            p.master.reverb_density = int(FDN_MAX_LINES as i32, FDN_MAX_LINES as i32);
+        };
+        let eco = render(&|p| {
+            costly(p);
+            p.master.eco = BoolParam::new("Test", true);
+        });
+        // Plain tanh and a single line, asked for directly
+        let cheap = render(&|p| {
+            p.master.drive_model = int(0, DRIVE_MODELS.len() as i32 - 1);
+            p.master.reverb_density = int(1, FDN_MAX_LINES as i32);
+        });
+        assert_eq!(eco, cheap);
+        // And the costly paths do sound different when Eco is off
+        assert_ne!(render(&costly), cheap);
+    }
+
+    #[test]
+    fn decay_aware_send_gives_short_hits_less_reverb() {
+        // Energy of the reverb's share of the output for a snare hit
+        let wet = |decay: f32, follow: bool| {
+            let edit = |p: &mut DrumParams, reverb: f32| {
+                dry(p);
+                p.master.reverb = float(reverb, 0.0, 1.0);
+                p.master.send_follows_decay = BoolParam::new("Test", follow);
+                p.snare.decay = float(decay, 10.0, 2000.0);
+            };
+            let hits = [note_on(0, 38, 1.0)];
+            let mut with = plugin(|p| edit(p, 1.0));
+            let mut without = plugin(|p| edit(p, 0.0));
+            let (out, direct) = (
+                run(&mut with, 24000, &hits),
+                run(&mut without, 24000, &hits),
+            );
+            // At full amount the dry path sits at 0.4
+            let wet: Vec<f32> = out[0]
+                .iter()
+                .zip(&direct[0])
+                .map(|(o, d)| o - 0.4 * d)
+                .collect();
+            rms(&wet)
+        };
+        let (short, long) = (wet(25.0, true), wet(800.0, true));
+        assert!(short < long, "{short} vs {long}");
+        // The short hit's send is cut to a quarter; the long one keeps all of it
+        assert!((short / wet(25.0, false) - 0.25).abs() < 0.01);
+        assert!((long / wet(800.0, false) - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn correlation_meter_reads_mono_and_inverted_pairs() {
+        // A second of 220 Hz with the right channel derived from the left
+        let settle = |right: fn(f32, f32) -> f32| {
+            let mut meter = CorrelationMeter::new(SR);
+            for n in 0..SR as usize {
+                let phase = std::f32::consts::TAU * 220.0 * n as f32 / SR;
+                meter.process(phase.sin(), right(phase.sin(), phase.cos()));
+            }
+            meter.value()
+        };
+        assert!((settle(|s, _| s) - 1.0).abs() < 1e-3);
+        assert!((settle(|s, _| -s) + 1.0).abs() < 1e-3);
+        // A quarter cycle apart shares nothing
+        assert!(settle(|_, c| c).abs() < 0.05);
+        // Nothing playing reads zero, not noise
+        assert_eq!(CorrelationMeter::new(SR).value(), 0.0);
+
+        // The plugin publishes it: a centred kick is mono
+        let mut drums = plugin(dry);
+        assert_eq!(drums.correlation(), 0.0);
+        let meter = drums.correlation_meter();
+        run(&mut drums, 4800, &[note_on(0, 36, 1.0)]);
+        assert!((drums.correlation() - 1.0).abs() < 1e-3);
+        assert_eq!(
+            f32::from_bits(meter.load(Ordering::Relaxed)),
+            drums.correlation()
+        );
+    }
+
+    #[test]
+    fn reverb_that_cannot_allocate_is_bypassed() {
+        let hits = [note_on(0, 36, 1.0), note_on(4800, 38, 0.9)];
+        let mut drums = plugin(|p| p.master.reverb = float(0.6, 0.0, 1.0));
+        let diagnostics = drums.diagnostics();
+
+        // Re-initialize with the reverb capped far below what a room needs
+        drums.reverb.max_len = 64;
+        let config = BufferConfig {
+            sample_rate: SR,
+            min_buffer_size: None,
+            max_buffer_size: BLOCK as u32,
+            process_mode: ProcessMode::Realtime,
+        };
+        assert!(drums.initialize(&Drumini::AUDIO_IO_LAYOUTS[0], &config, &mut TestInit));
+        drums.reset();
+        assert_eq!(diagnostics.snapshot().reverb_alloc_failures, 1);
+        assert!(drums.reverb.bypassed);
+
+        // The kit plays on, as if the reverb were turned down
+        let bypassed = run(&mut drums, 24000, &hits);
+        let mut no_room = plugin(|p| p.master.reverb = float(0.0, 0.0, 1.0));
+        assert_eq!(bypassed, run(&mut no_room, 24000, &hits));
+        assert!(peak(&bypassed[0]) > 0.1);
+
+        // Given room again, it comes back
+        drums.reverb.max_len = usize::MAX;
+        assert!(drums.initialize(&Drumini::AUDIO_IO_LAYOUTS[0], &config, &mut TestInit));
+        drums.reset();
+        assert!(!drums.reverb.bypassed);
+        assert_eq!(diagnostics.snapshot().reverb_alloc_failures, 1);
+        assert_ne!(run(&mut drums, 24000, &hits), bypassed);
+    }
+
+    #[test]
+    fn comp_bypassed_hats_keep_their_dynamics() {
+        let render = |comp: f32, bypass: bool, hits: &[PluginNoteEvent<Drumini>]| {
+            let mut drums = plugin(|p| {
+                dry(p);
+                p.master.comp = float(comp, 0.0, 1.0);
+                // Loud enough to be well over the threshold
+                p.kick.level = float(2.0, 0.0, 2.0);
+                p.hat_closed.comp_bypass = BoolParam::new("Test", bypass);
+            });
+            run(&mut drums, 9600, hits)
+        };
+        let kick = [note_on(0, 36, 1.0)];
+        let both = [
+            note_on(0, 36, 1.0),
+            note_on(480, 42, 1.0),
+            note_on(4800, 42, 1.0),
+        ];
+
+        // The kick is still squashed once the comp has caught it
+        let body = |out: [Vec<f32>; 2]| rms(&out[0][960..4800]);
+        assert!(body(render(1.0, true, &kick)) < 0.8 * body(render(0.0, true, &kick)));
+
+        // What the hats add over the kick is exactly their uncompressed selves
+        let mix = render(1.0, true, &both);
+        let kick_only = render(1.0, true, &kick);
+        let hats = render(0.0, true, &both[1..]);
+        for ch in 0..2 {
+            for ((m, k), h) in mix[ch].iter().zip(&kick_only[ch]).zip(&hats[ch]) {
+                assert!((m - k - h).abs() < 1e-5, "{m} - {k} vs {h}");
+            }
+        }
+        // Through the comp, they'd have been ducked under the kick
+        let ducked = render(1.0, false, &both);
+        let added = |out: &[Vec<f32>; 2]| {
+            let hat: Vec<f32> = out[0][480..2400]
+                .iter()
+                .zip(&kick_only[0][480..2400])
+                .map(|(m, k)| m - k)
+                .collect();
+            rms(&hat)
+        };
+        assert!(added(&ducked) < 0.9 * added(&mix));
+    }
+
+    #[test]
+    fn synced_lfo_bends_the_tom_with_the_bar() {
+        let bar = LFO_BEATS.iter().position(|&b| b == 4.0).unwrap() as i32;
+        let lfo = |depth: f32| {
+            plugin(|p| {
+                dry(p);
+                let max = LFO_BEATS.len() as i32 - 1;
+                p.master.lfo_rate = IntParam::new("Test", bar, IntRange::Linear { min: 0, max });
+                p.master.lfo_dest = IntParam::new("Test", 2, IntRange::Linear { min: 0, max: 2 });
+                p.master.lfo_depth = float(depth, 0.0, 1.0);
+            })
+        };
+        // The tom's strongest partial for a hit at song position `beat`, 120 BPM
+        let fundamental = |depth: f32, beat: f64| {
+            let playing = TransportSnapshot {
+                playing: true,
+                tempo: Some(120.0),
+                pos_beats: Some(beat),
+                sample_rate: SR,
+            };
+            let [l, _] = run_with(&mut lfo(depth), 9600, playing, &[note_on(0, 45, 0.8)]);
+            (20..800)
+                .map(|f| (f, analysis::goertzel(&l[2400..], SR, f as f32)))
+                .fold(
+                    (0, 0.0),
+                    |best, (f, m)| if m > best.1 { (f, m) } else { best },
+                )
+                .0 as f32
+        };
+        // A quarter of the way through the bar the LFO peaks, three quarters it dips
+        let plain = fundamental(0.0, 1.0);
+        let (up, down) = (fundamental(1.0, 1.0), fundamental(1.0, 3.0));
+        assert!((up / plain - 2.0).abs() < 0.05, "{plain} -> {up}");
+        assert!((down / plain - 0.5).abs() < 0.05, "{plain} -> {down}");
+        // On the downbeat it sits at the centre
+        assert!((fundamental(1.0, 0.0) / plain - 1.0).abs() < 0.05);
+
+        // Without a transport it free-runs at the fallback tempo
+        let mut drums = lfo(1.0);
+        let frames = 10 * BLOCK;
+        run(&mut drums, frames, &[]);
+        let bar_samples = 4.0 * 60.0 / LFO_FALLBACK_BPM * SR as f64;
+        assert!((drums.lfo_phase - frames as f64 / bar_samples).abs() < 1e-9);
+    }
+
+    #[test]
+    fn note_priority_picks_which_tom_note_plays() {
+        let render = |priority: i32, hits: &[PluginNoteEvent<Drumini>]| {
+            let mut drums = plugin(|p| {
+                dry(p);
+                let max = NOTE_PRIORITIES.len() as i32 - 1;
+                p.master.note_priority =
+                    IntParam::new("Test", priority, IntRange::Linear { min: 0, max });
+            });
+            run(&mut drums, 9600, hits)
+        };
+        // A loud high tom, then 5 ms later a quiet low one on the same slot
+        let high = note_on(0, 47, 1.0);
+        let both = [high, note_on(240, 43, 0.3)];
+
+        // Last: the low tom cuts in, and its pitch rings on
+        let pitch = |out: &[Vec<f32>; 2]| {
+            (20..400)
+                .map(|f: i32| (f, analysis::goertzel(&out[0][2400..], SR, f as f32)))
+                .fold(
+                    (0, 0.0),
+                    |best, (f, m)| if m > best.1 { (f, m) } else { best },
+                )
+                .0
+        };
+        let last = render(0, &both);
+        assert!((pitch(&last) - pitch(&render(0, &[both[1]]))).abs() <= 2);
+        assert!(pitch(&last) < pitch(&render(0, &[high])));
+        // Highest: it can't cut the high one short
+        assert!(render(1, &both) == render(1, &[high]));
+        // Loudest: too quiet to take over...
+        assert!(render(2, &both) == render(2, &[high]));
+        // ...but a hard low hit over a soft high one does
+        let soft_high = note_on(0, 47, 0.3);
+        let hard_low = [soft_high, note_on(240, 43, 1.0)];
+        assert!(pitch(&render(2, &hard_low)) < pitch(&render(2, &[soft_high])));
+    }
+
+    #[test]
+    fn balance_trims_the_sides_without_moving_the_image() {
+        let hits = [note_on(0, 36, 1.0), note_on(1200, 38, 0.9)];
+        let render = |balance: Option<f32>| {
+            let mut drums = plugin(|p| {
+                p.snare.pan = float(-0.5, -1.0, 1.0);
+                if let Some(balance) = balance {
+                    p.master.balance = float(balance, -1.0, 1.0);
+                }
+            });
+            run(&mut drums, 9600, &hits)
+        };
+        // Centred, the default, it leaves the kit alone
+        let [l, r] = render(None);
+        assert!(render(Some(0.0)) == [l.clone(), r.clone()]);
+
+        // Right of centre cuts the left side and leaves the right at unity
+        let [bl, br] = render(Some(0.5));
+        assert!(rms(&bl) < rms(&l));
+        assert!(br == r);
+        // A plain gain: the same signal, just scaled, and never louder
+        assert!(bl.iter().zip(&l).all(|(b, x)| (b - x * 0.5).abs() < 1e-6));
+
+        let [bl, br] = render(Some(-1.0));
+        assert!(bl == l);
+        assert!(br.iter().all(|&x| x == 0.0));
+    }
+
+    #[test]
+    fn probability_plays_about_half_the_notes_and_repeats_with_the_seed() {
+        // 200 short hats, 25 ms apart: which of them sounded
+        let played = |probability: f32| {
+            let mut drums = plugin(|p| {
+                dry(p);
+                p.hat_closed.decay = float(10.0, 10.0, 2000.0);
+                p.hat_closed.probability = float(probability, 0.0, 1.0);
+            });
+            let hits: Vec<_> = (0..200).map(|i| note_on(i * 1200, 42, 1.0)).collect();
+            let [l, _] = run(&mut drums, 200 * 1200, &hits);
+            l.chunks(1200)
+                .map(|hit| peak(hit) > 0.01)
+                .collect::<Vec<_>>()
+        };
+        let half = played(0.5);
+        let count = half.iter().filter(|&&p| p).count();
+        assert!((70..=130).contains(&count), "{count} of 200");
+        // The same seed gives the same variation
+        assert_eq!(played(0.5), half);
+        // Certain at 1, never at 0
+        assert!(played(1.0).iter().all(|&p| p));
+        assert!(!played(0.0).iter().any(|&p| p));
+    }
+
+    #[test]
+    fn automation_glides_the_same_whatever_the_block_size() {
+        // A snare with its tone, level and the master drive all moving at once
+        let render = |block: usize| {
+            let mut drums = plugin(|_| {});
+            let p = &drums.params;
+            p.snare.tone.smoothed.set_target(SR, 0.9);
+            p.snare.level.smoothed.set_target(SR, 0.3);
+            p.master.drive.smoothed.set_target(SR, 0.8);
+            let (mut l, mut r) = (vec![0.0; 4800], vec![0.0; 4800]);
+            let mut hit = Some(note_on(0, 38, 1.0));
+            for start in (0..4800).step_by(block) {
+                let end = (start + block).min(4800);
+                let mut outputs = [&mut l[start..end], &mut r[start..end]];
+                drums.render(&mut outputs, end - start, stopped(), || hit.take());
+            }
+            (l, r)
+        };
+        let reference = render(BLOCK);
+        // Gliding over the 20 ms, in the same steps sample for sample
+        for block in [1, 32, 100, 251] {
+            assert!(render(block) == reference, "{block}-sample blocks");
+        }
+        let mut settled = plugin(|_| {});
+        let [l, _] = run(&mut settled, 4800, &[note_on(0, 38, 1.0)]);
+        assert!(l != reference.0);
+    }
+
+    #[test]
+    fn stereo_noise_decorrelates_a_centred_clap() {
+        let render = |stereo: f32| {
+            let mut drums = plugin(|p| {
+                dry(p);
+                p.clap.stereo = float(stereo, 0.0, 1.0);
+            });
+            run(&mut drums, 9600, &[note_on(0, 39, 1.0)])
+        };
+        let [l, r] = render(0.0);
+        assert!((correlation(&l, &r) - 1.0).abs() < 1e-4);
+        let [l, r] = render(1.0);
+        assert!(correlation(&l, &r).abs() < 0.2, "{}", correlation(&l, &r));
+        // Halfway keeps some of the mono noise in both sides
+        let [l, r] = render(0.5);
+        assert!((0.2..0.9).contains(&correlation(&l, &r)));
+        // As loud either way
+        let ([ml, _], [sl, sr]) = (render(0.0), render(1.0));
+        assert!((rms(&sl) / rms(&ml) - 1.0).abs() < 0.2);
+        assert!((rms(&sr) / rms(&ml) - 1.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn param_ids_are_unique() {
+        let params = DrumParams::default();
+        let map = params.param_map();
+        let ids: std::collections::HashSet<_> = map.iter().map(|(id, _, _)| id).collect();
+        assert_eq!(ids.len(), map.len());
+        // Every slot's stereo amount among them, under its own id
+        for group in ["kick", "snare", "clap", "hatc", "hato", "tom", "pc1", "pc2"] {
+            let stereo = format!("{group}_stn");
+            assert!(ids.contains(&stereo), "{stereo}");
+        }
+    }
+
+    #[test]
+    fn vu_meter_moves_slower_than_peak() {
+        // A 300 ms, 1 kHz burst at half scale, then half a second of silence
+        let readings = |mode: usize| {
+            let mut meter = LevelMeter::new(SR);
+            meter.set_mode(mode);
+            (0..(0.8 * SR) as usize)
+                .map(|n| {
+                    let x = if n < (0.3 * SR) as usize {
+                        0.5 * (std::f32::consts::TAU * 1000.0 * n as f32 / SR).sin()
+                    } else {
+                        0.0
+                    };
+                    meter.process(x, x);
+                    meter.value()
+                })
+                .collect::<Vec<f32>>()
+        };
+        let vu_mode = METER_MODES.iter().position(|&m| m == "VU").unwrap();
+        let (peak, vu) = (readings(0), readings(vu_mode));
+        // Samples until each reads 90% of where it sits at the end of the burst
+        let end = (0.3 * SR) as usize - 1;
+        let rise = |r: &[f32]| r.iter().position(|&v| v >= 0.9 * r[end]).unwrap();
+        assert!(rise(&peak) < (0.001 * SR) as usize);
+        assert!(rise(&vu) > (0.1 * SR) as usize, "{}", rise(&vu));
+        // Peak catches the crest, VU the rectified average
+        assert!((peak[end] - 0.5).abs() < 0.01);
+        assert!((vu[end] - 0.5 * 2.0 / std::f32::consts::PI).abs() < 0.02);
+        // The VU needle never jumps: its biggest step is a fraction of peak's
+        let step = |r: &[f32]| {
+            r.windows(2)
+                .map(|w| (w[1] - w[0]).abs())
+                .fold(0.0, f32::max)
+        };
+        assert!(step(&vu) < 0.05 * step(&peak));
+        // After the burst it falls back over about as long as it took to rise
+        let fall = vu[end..].iter().position(|&v| v <= 0.1 * vu[end]).unwrap();
+        assert!(fall > rise(&vu) / 2, "fell in {fall}");
+    }
+}
@@ -1,4 +1,17 @@
+use crate::drum_engine::{
+    BODY_WAVES, HUMANIZE_DISTS, N_SLOTS, NOISE_FILTERS, PITCH_MAX_HZ, SCALES, SampleLayer,
+};
+use crate::kits::{
+    AbCompare, FACTORY_KITS, KitOverride, KitSnapshot, MASTER_MACROS, MasterValues, N_USER_KITS,
+    SLOT_MACROS, SlotValues,
+};
+use crate::{
+    COMP_ATTACK_MS, COMP_RELEASE_MS, DRIVE_MODELS, ER_PATTERNS, FDN_MAX_LINES, GROUP_NAMES,
+    LFO_DESTINATIONS, LFO_RATES, METER_MODES, N_GROUPS, NOTE_PRIORITIES, PAN_LAWS, PRE_VERB_HP_MIN,
+    PRE_VERB_LP_MAX,
+};
 use nih_plug::prelude::*;
+use std::sync::{Arc, RwLock};
 
 /// Top-level parameters: 8 drum slots + master section.
 #[derive(Params)]
@@ -29,6 +42,27 @@ pub struct DrumParams {
 
     #[nested(group = "Master")]
     pub master: MasterParams,
+
+    #[nested(array, group = "Group")]
+    pub groups: [GroupParams; N_GROUPS],
+
+    /// The factory kit laid over the host's macro values, so it survives a reload
+    #[persist = "kit-override"]
+    pub kit_override: Arc<RwLock<Option<KitOverride>>>,
+
+    /// User kits saved with the plugin state
+    #[persist = "user-kits"]
+    pub user_kits: Arc<RwLock<[Option<KitSnapshot>; N_USER_KITS]>>,
+
+    /// Per-slot sample layers saved with the plugin state. The PCM goes in raw, as
+    /// there's no file to point back to; the length cap keeps the worst case to
+    /// `MAX_LAYER_SAMPLES` values per slot
+    #[persist = "slot-layers"]
+    pub slot_layers: Arc<RwLock<[Option<SampleLayer>; N_SLOTS]>>,
+
+    /// A/B compare states saved with the plugin state
+    #[persist = "ab-compare"]
+    pub ab_compare: Arc<RwLock<AbCompare>>,
 }
 
 /// Parameters for a single drum slot (Kick/Snare/…)
@@ -61,6 +95,198 @@ pub struct DrumSlotParams {
     /// Humanization amount (randomization of level/decay/pitch)
     #[id = "hum"]
     pub humanize: FloatParam,
+
+    /// Tone response curve (0 = linear, ± bends the sweep's midpoint)
+    #[id = "tcv"]
+    pub tone_curve: FloatParam,
+
+    /// Flam amount: a delayed, quieter second hit (0 = off)
+    #[id = "flm"]
+    pub flam: FloatParam,
+
+    /// Haas widening: delays one channel by up to a few ms (0 = off)
+    #[id = "has"]
+    pub haas: FloatParam,
+
+    /// Reverb send amount
+    #[id = "rsn"]
+    pub reverb_send: FloatParam,
+
+    /// Humanize/noise RNG seed, reapplied on reset so grooves reproduce on reload
+    #[id = "sed"]
+    pub seed: IntParam,
+
+    /// Reverse envelope: swell up over the decay, then cut
+    #[id = "rev"]
+    pub reverse: BoolParam,
+
+    /// Initial envelope level; lower values skip into the decay (1 = full hit)
+    #[id = "sto"]
+    pub start: FloatParam,
+
+    /// Portamento from the previous hit's pitch (0 = instant)
+    #[id = "gld"]
+    pub glide: FloatParam,
+
+    /// 3-band EQ gains (dB): low shelf, mid bell, high shelf
+    #[id = "eql"]
+    pub eq_low: FloatParam,
+
+    #[id = "eqm"]
+    pub eq_mid: FloatParam,
+
+    #[id = "eqh"]
+    pub eq_high: FloatParam,
+
+    /// Waveform of the pitched body (kick, snare, tom, percs)
+    #[id = "bdw"]
+    pub body_wave: IntParam,
+
+    /// Tone offsets for the softest and hardest hits, crossfaded by velocity
+    #[id = "tsf"]
+    pub tone_soft: FloatParam,
+
+    #[id = "thd"]
+    pub tone_hard: FloatParam,
+
+    /// Noise filter character: high-pass, band-pass or low-pass
+    #[id = "nft"]
+    pub noise_filter: IntParam,
+
+    /// Sub-octave sine under the body (kick only)
+    #[id = "sub"]
+    pub sub: FloatParam,
+
+    /// Keep the noise filter warm across retriggers, for even rolls
+    #[id = "srl"]
+    pub smooth_rolls: BoolParam,
+
+    /// Ignore retriggers quieter than the hit still sounding (off = newest wins)
+    #[id = "lwn"]
+    pub loudest_wins: BoolParam,
+
+    /// Envelope to noise-filter cutoff: positive opens on the attack, negative closes
+    #[id = "enf"]
+    pub env_filter: FloatParam,
+
+    /// Keep loudness steady as tone moves (holds the level from when it was enabled)
+    #[id = "tcp"]
+    pub tone_comp: BoolParam,
+
+    /// Level of the slot's sample layer, if one is loaded
+    #[id = "lyl"]
+    pub layer_level: FloatParam,
+
+    /// Skip the slot's output saturator for an uncoloured signal
+    #[id = "cln"]
+    pub clean: BoolParam,
+
+    /// Sub-mix group this slot is routed through
+    #[id = "grp"]
+    pub group: IntParam,
+
+    /// Velocity to saturator drive: hard hits get dirtier (0 = off)
+    #[id = "vdr"]
+    pub vel_drive: FloatParam,
+
+    /// Noise cutoff follows the slot's pitch, so transposing darkens or brightens it
+    #[id = "ktk"]
+    pub key_track: BoolParam,
+
+    /// Pan law for just this slot, or "Master" to follow the master setting
+    #[id = "spl"]
+    pub pan_law: IntParam,
+
+    /// Pitched ring against noise, from clap-like to metallic (Perc 1 only)
+    #[id = "rng"]
+    pub ring: FloatParam,
+
+    /// Send only the hit's attack to the reverb instead of its whole tail
+    #[id = "gsd"]
+    pub gated_send: BoolParam,
+
+    /// Quantize the slot's pitch (tuning, kit pitch, humanize) to a scale
+    #[id = "qnt"]
+    pub scale: IntParam,
+
+    /// Lowest velocity a hit plays at, so soft pad taps stay audible
+    #[id = "vfl"]
+    pub vel_floor: FloatParam,
+
+    /// Highest velocity a hit plays at, to tame harsh accents
+    #[id = "vcl"]
+    pub vel_ceiling: FloatParam,
+
+    /// Short broadband noise burst on the attack (kick, snare and tom only)
+    #[id = "atn"]
+    pub attack_noise: FloatParam,
+
+    /// Shorten the decay as the slot is tuned up, and lengthen it tuned down
+    #[id = "dlk"]
+    pub decay_link: FloatParam,
+
+    /// Randomize the body's starting phase per hit, so repeats aren't identical
+    #[id = "phr"]
+    pub phase_random: FloatParam,
+
+    /// Let tone also move the body/noise balance and drive, not just brightness
+    #[id = "wtn"]
+    pub wide_tone: BoolParam,
+
+    /// Keep this slot out of the master compressor; it still goes through its group
+    /// bus and the rest of the master chain
+    #[id = "cbp"]
+    pub comp_bypass: BoolParam,
+
+    /// Body distortion relative to snap (kick only; 0 = follows snap)
+    #[id = "bdd"]
+    pub body_drive: FloatParam,
+
+    /// Click level relative to snap (kick only; 0 = follows snap)
+    #[id = "clk"]
+    pub click: FloatParam,
+
+    /// Chance each played note triggers the slot, for evolving loops
+    #[id = "prb"]
+    pub probability: FloatParam,
+
+    /// Clap and hats: blend from mono noise to separate left/right noise streams
+    #[id = "stn"]
+    pub stereo: FloatParam,
+
+    /// Ceiling on the slot's pitch, so extreme pitch settings stay usable
+    #[id = "mxp"]
+    pub max_pitch: FloatParam,
+}
+
+/// A sub-mix bus between the slots and the master section.
+#[derive(Params)]
+pub struct GroupParams {
+    #[id = "gan"]
+    pub gain: FloatParam,
+
+    /// Bus compression for just this group (0 = off)
+    #[id = "cmp"]
+    pub comp: FloatParam,
+}
+
+impl GroupParams {
+    fn new(name: &str) -> Self {
+        Self {
+            gain: FloatParam::new(
+                format!("{name} Gain"),
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 2.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(20.0))
+            .with_unit("×"),
+            comp: FloatParam::new(
+                format!("{name} Comp"),
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            ),
+        }
+    }
 }
 
 /// Global/master controls.
@@ -85,6 +311,167 @@ pub struct MasterParams {
     /// Velocity curve / sensitivity
     #[id = "vel"]
     pub velocity_curve: FloatParam,
+
+    /// TPDF dither on the master output
+    #[id = "dth"]
+    pub dither: BoolParam,
+
+    /// Scale each slot's reverb send by its (smoothed) level
+    #[id = "sfl"]
+    pub send_follows_level: BoolParam,
+
+    /// Scale each slot's reverb send by its decay, so short hits get less tail
+    #[id = "sfd"]
+    pub send_follows_decay: BoolParam,
+
+    /// Factory kit; changing it glides the macros to the kit's values
+    #[id = "kit"]
+    pub kit: IntParam,
+
+    /// Program-dependent compressor release
+    #[id = "car"]
+    pub comp_auto_release: BoolParam,
+
+    /// Parallel compression: dry/compressed blend (1 = fully compressed)
+    #[id = "cpm"]
+    pub comp_mix: FloatParam,
+
+    /// Compressor attack; ignored by the group buses
+    #[id = "cat"]
+    pub comp_attack: FloatParam,
+
+    /// Compressor release when auto release is off
+    #[id = "crl"]
+    pub comp_release: FloatParam,
+
+    /// Crossover below which the output is summed to mono (0 = off)
+    #[id = "bmo"]
+    pub bass_mono: FloatParam,
+
+    /// High-shelf lift above ~8 kHz (0 = flat)
+    #[id = "air"]
+    pub air: FloatParam,
+
+    /// Reverb early-reflection pattern
+    #[id = "rer"]
+    pub reverb_pattern: IntParam,
+
+    /// Reverb tail density: how many feedback delay lines run
+    #[id = "rdn"]
+    pub reverb_density: IntParam,
+
+    /// Reverb tail decay time (RT60)
+    #[id = "rdc"]
+    pub reverb_decay: FloatParam,
+
+    /// High-pass on the reverb input, to keep the kick out of the tail
+    #[id = "rhp"]
+    pub reverb_hp: FloatParam,
+
+    /// Low-pass on the reverb input, to tame harsh highs in the tail
+    #[id = "rlp"]
+    pub reverb_lp: FloatParam,
+
+    /// Quickly fade the reverb tail when the host transport stops
+    #[id = "tos"]
+    pub cut_tail_on_stop: BoolParam,
+
+    /// Band-limit the noise engines to ~18 kHz at any sample rate
+    #[id = "gnz"]
+    pub gentle_noise: BoolParam,
+
+    /// One-knob feel: tight shortens decays, adds snap and removes humanize; loose does the opposite
+    #[id = "tgt"]
+    pub tightness: FloatParam,
+
+    /// Soft-limit the summed slots before the drive so pile-ups don't slam the chain
+    #[id = "sgd"]
+    pub sum_guard: BoolParam,
+
+    /// Late-hit jitter, scaled by each slot's humanize (0 = quantized)
+    #[id = "htm"]
+    pub humanize_timing: FloatParam,
+
+    /// Keep timing humanize out of offline bounces
+    #[id = "tlo"]
+    pub timing_live_only: BoolParam,
+
+    /// Negate the final output
+    #[id = "inv"]
+    pub invert_phase: BoolParam,
+
+    /// Compress each channel on its own instead of stereo-linked
+    #[id = "cdm"]
+    pub comp_dual_mono: BoolParam,
+
+    /// Trim the summed mid channel to offset center build-up from equal-power panning
+    #[id = "ccp"]
+    pub center_comp: BoolParam,
+
+    /// Saturation character of the master drive
+    #[id = "dmd"]
+    pub drive_model: IntParam,
+
+    /// Allpass widening of centred material, mono-compatible (0 = off)
+    #[id = "stz"]
+    pub stereoize: FloatParam,
+
+    /// End voices at a fixed output level instead of a fixed envelope level
+    #[id = "rtl"]
+    pub relative_tails: BoolParam,
+
+    /// How slot pan positions map to left/right gains
+    #[id = "pnl"]
+    pub pan_law: IntParam,
+
+    /// Replace the output with the compressor's detection signal, for monitoring
+    #[id = "scl"]
+    pub sc_listen: BoolParam,
+
+    /// Sum the output to mono on both channels, for checking mono compatibility
+    #[id = "mmn"]
+    pub mono_monitor: BoolParam,
+
+    /// Reference pitch of A4, scaling every slot's pitch
+    #[id = "tun"]
+    pub tuning: FloatParam,
+
+    /// Low-CPU mode: sine bodies, no sample layers, stereo noise or slot EQ, the
+    /// plain tanh drive, a one-line reverb tail and no stereoize
+    #[id = "eco"]
+    pub eco: BoolParam,
+
+    /// Shape of the per-hit humanize draws: flat or clustered around no change
+    #[id = "hdi"]
+    pub humanize_dist: IntParam,
+
+    /// Tempo-synced LFO period
+    #[id = "lfr"]
+    pub lfo_rate: IntParam,
+
+    /// LFO amount on its destination (0 = off)
+    #[id = "lfd"]
+    pub lfo_depth: FloatParam,
+
+    /// What the LFO modulates: reverb amount, every slot's tone, or every slot's pitch
+    #[id = "lfx"]
+    pub lfo_dest: IntParam,
+
+    /// Which of several notes sharing a slot (the toms) gets to retrigger it
+    #[id = "npr"]
+    pub note_priority: IntParam,
+
+    /// Output L/R trim for monitoring imbalances; leaves the panning alone
+    #[id = "bal"]
+    pub balance: FloatParam,
+
+    /// Replay each slot's last few humanize draws in a loop instead of rolling new ones
+    #[id = "fzh"]
+    pub freeze_humanize: BoolParam,
+
+    /// Ballistics of the output level meter
+    #[id = "mtm"]
+    pub meter_mode: IntParam,
 }
 
 impl Default for DrumParams {
@@ -99,11 +486,83 @@ impl Default for DrumParams {
             perc1: DrumSlotParams::default_perc1(),
             perc2: DrumSlotParams::default_perc2(),
             master: MasterParams::default(),
+            groups: core::array::from_fn(|g| GroupParams::new(GROUP_NAMES[g])),
+            kit_override: Arc::new(RwLock::new(None)),
+            user_kits: Arc::new(RwLock::new(Default::default())),
+            slot_layers: Arc::new(RwLock::new(Default::default())),
+            ab_compare: Arc::new(RwLock::new(Default::default())),
+        }
+    }
+}
+
+impl DrumParams {
+    /// Slot parameters by index, in `SLOT_TYPES` order.
+    pub fn slot(&self, index: usize) -> &DrumSlotParams {
+        match index {
+            0 => &self.kick,
+            1 => &self.snare,
+            2 => &self.clap,
+            3 => &self.hat_closed,
+            4 => &self.hat_open,
+            5 => &self.tom,
+            6 => &self.perc1,
+            7 => &self.perc2,
+            _ => &self.kick,
+        }
+    }
+
+    /// Advance every smoother by `samples`, so a block skipped while silent
+    /// leaves them where rendering it would have.
+    pub fn skip_smoothers(&self, samples: u32) {
+        for i in 0..N_SLOTS {
+            self.slot(i).skip_smoothers(samples);
+        }
+        self.master.skip_smoothers(samples);
+        for group in &self.groups {
+            group.gain.smoothed.next_step(samples);
         }
     }
 }
 
+// The engines read macros through `.smoothed`, so retargeting a smoother (kits)
+// changes the sound without allocating or touching the host's values.
+
 impl DrumSlotParams {
+    /// Advance every smoother by `samples`, for blocks that skip rendering.
+    fn skip_smoothers(&self, samples: u32) {
+        self.level.smoothed.next_step(samples);
+        self.pan.smoothed.next_step(samples);
+        self.tone.smoothed.next_step(samples);
+        self.decay.smoothed.next_step(samples);
+        self.snap.smoothed.next_step(samples);
+        self.pitch.smoothed.next_step(samples);
+        self.humanize.smoothed.next_step(samples);
+    }
+
+    /// Glide the macros towards `v` (see `SlotValues` for the order).
+    pub fn glide_to(&self, v: &SlotValues, sample_rate: f32) {
+        self.level.smoothed.set_target(sample_rate, v[0]);
+        self.pan.smoothed.set_target(sample_rate, v[1]);
+        self.tone.smoothed.set_target(sample_rate, v[2]);
+        self.decay.smoothed.set_target(sample_rate, v[3]);
+        self.snap.smoothed.set_target(sample_rate, v[4]);
+        self.pitch.smoothed.set_target(sample_rate, v[5]);
+        self.humanize.smoothed.set_target(sample_rate, v[6]);
+    }
+
+    /// The macro params, in `SlotValues` order.
+    pub fn macros(&self) -> [&FloatParam; SLOT_MACROS] {
+        [
+            &self.level,
+            &self.pan,
+            &self.tone,
+            &self.decay,
+            &self.snap,
+            &self.pitch,
+            &self.humanize,
+        ]
+    }
+
     /// Construct a slot with explicit values for all macros.
     pub fn from_values(
         label: &str, // NEW: "Kick", "Snare", etc.
@@ -122,6 +581,48 @@ impl DrumSlotParams {
         let snap_name = format!("{label} Snap");
         let pitch_name = format!("{label} Pitch");
         let hum_name = format!("{label} Humanize");
+        let tone_curve_name = format!("{label} Tone Curve");
+        let flam_name = format!("{label} Flam");
+        let haas_name = format!("{label} Haas");
+        let send_name = format!("{label} Reverb Send");
+        let seed_name = format!("{label} Seed");
+        let reverse_name = format!("{label} Reverse");
+        let start_name = format!("{label} Start");
+        let glide_name = format!("{label} Glide");
+        let body_wave_name = format!("{label} Body Wave");
+        let tone_soft_name = format!("{label} Soft Tone");
+        let tone_hard_name = format!("{label} Hard Tone");
+        let noise_filter_name = format!("{label} Noise Filter");
+        let sub_name = format!("{label} Sub");
+        let smooth_rolls_name = format!("{label} Smooth Rolls");
+        let loudest_wins_name = format!("{label} Loudest Wins");
+        let env_filter_name = format!("{label} Env Filter");
+        let tone_comp_name = format!("{label} Tone Loudness Comp");
+        let layer_level_name = format!("{label} Layer Level");
+        let clean_name = format!("{label} Clean");
+        let group_name = format!("{label} Group");
+        let vel_drive_name = format!("{label} Vel Drive");
+        let key_track_name = format!("{label} Key Track");
+        let pan_law_name = format!("{label} Pan Law");
+        let ring_name = format!("{label} Ring");
+        let gated_send_name = format!("{label} Gated Send");
+        let scale_name = format!("{label} Scale");
+        let vel_floor_name = format!("{label} Vel Floor");
+        let vel_ceiling_name = format!("{label} Vel Ceiling");
+        let attack_noise_name = format!("{label} Attack Noise");
+        let decay_link_name = format!("{label} Decay Link");
+        let phase_random_name = format!("{label} Phase Random");
+        let wide_tone_name = format!("{label} Wide Tone");
+        let comp_bypass_name = format!("{label} Comp Bypass");
+        let body_drive_name = format!("{label} Body Drive");
+        let click_name = format!("{label} Click");
+        let probability_name = format!("{label} Probability");
+        let stereo_name = format!("{label} Stereo");
+        let max_pitch_name = format!("{label} Max Pitch");
+        let eq_range = FloatRange::Linear {
+            min: -12.0,
+            max: 12.0,
+        };
 
         Self {
             level: FloatParam::new(
@@ -129,6 +630,7 @@ impl DrumSlotParams {
                 level,
                 FloatRange::Linear { min: 0.0, max: 2.0 },
             )
+            .with_smoother(SmoothingStyle::Linear(20.0))
             .with_unit("×"),
 
             pan: FloatParam::new(
@@ -138,9 +640,11 @@ impl DrumSlotParams {
                     min: -1.0,
                     max: 1.0,
                 },
-            ),
+            )
+            .with_smoother(SmoothingStyle::Linear(20.0)),
 
-            tone: FloatParam::new(&tone_name, tone, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            tone: FloatParam::new(&tone_name, tone, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(20.0)),
 
             decay: FloatParam::new(
                 &decay_name,
@@ -151,9 +655,11 @@ impl DrumSlotParams {
                     factor: 0.4,
                 },
             )
-            .with_unit("ms"),
+            .with_unit("ms")
+            .with_smoother(SmoothingStyle::Linear(20.0)),
 
-            snap: FloatParam::new(&snap_name, snap, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            snap: FloatParam::new(&snap_name, snap, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(20.0)),
 
             pitch: FloatParam::new(
                 &pitch_name,
@@ -163,13 +669,260 @@ impl DrumSlotParams {
                     max: 24.0,
                 },
             )
-            .with_unit("st"),
+            .with_unit("st")
+            .with_smoother(SmoothingStyle::Linear(20.0)),
 
             humanize: FloatParam::new(
                 &hum_name,
                 humanize,
                 FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(20.0)),
+
+            tone_curve: FloatParam::new(
+                &tone_curve_name,
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                },
             ),
+
+            flam: FloatParam::new(&flam_name, 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+
+            haas: FloatParam::new(&haas_name, 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+
+            reverb_send: FloatParam::new(
+                &send_name,
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            ),
+
+            seed: IntParam::new(
+                &seed_name,
+                default_seed(label),
+                IntRange::Linear {
+                    min: 1,
+                    max: MAX_SEED,
+                },
+            ),
+
+            reverse: BoolParam::new(&reverse_name, false),
+
+            start: FloatParam::new(&start_name, 1.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+
+            glide: FloatParam::new(
+                &glide_name,
+                0.0,
+                FloatRange::Skewed {
+                    min: 0.0,
+                    max: 500.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit("ms"),
+
+            eq_low: FloatParam::new(format!("{label} EQ Low"), 0.0, eq_range).with_unit(" dB"),
+            eq_mid: FloatParam::new(format!("{label} EQ Mid"), 0.0, eq_range).with_unit(" dB"),
+            eq_high: FloatParam::new(format!("{label} EQ High"), 0.0, eq_range).with_unit(" dB"),
+
+            body_wave: IntParam::new(
+                &body_wave_name,
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: BODY_WAVES.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|v| {
+                BODY_WAVES
+                    .get(v as usize)
+                    .map_or_else(String::new, |name| name.to_string())
+            })),
+
+            tone_soft: FloatParam::new(
+                &tone_soft_name,
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                },
+            ),
+
+            tone_hard: FloatParam::new(
+                &tone_hard_name,
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                },
+            ),
+
+            noise_filter: IntParam::new(
+                &noise_filter_name,
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: NOISE_FILTERS.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|v| {
+                NOISE_FILTERS
+                    .get(v as usize)
+                    .map_or_else(String::new, |name| name.to_string())
+            })),
+
+            sub: FloatParam::new(&sub_name, 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+
+            smooth_rolls: BoolParam::new(&smooth_rolls_name, false),
+
+            loudest_wins: BoolParam::new(&loudest_wins_name, false),
+
+            env_filter: FloatParam::new(
+                &env_filter_name,
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                },
+            ),
+
+            tone_comp: BoolParam::new(&tone_comp_name, false),
+
+            layer_level: FloatParam::new(
+                &layer_level_name,
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 2.0 },
+            )
+            .with_unit("×"),
+
+            clean: BoolParam::new(&clean_name, false),
+
+            group: IntParam::new(
+                &group_name,
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: N_GROUPS as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|v| {
+                GROUP_NAMES
+                    .get(v as usize)
+                    .map_or_else(String::new, |name| name.to_string())
+            })),
+
+            vel_drive: FloatParam::new(
+                &vel_drive_name,
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            ),
+
+            key_track: BoolParam::new(&key_track_name, false),
+
+            pan_law: IntParam::new(
+                &pan_law_name,
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: PAN_LAWS.len() as i32,
+                },
+            )
+            .with_value_to_string(Arc::new(|v| match v {
+                0 => String::from("Master"),
+                v => PAN_LAWS
+                    .get(v as usize - 1)
+                    .map_or_else(String::new, |name| name.to_string()),
+            })),
+
+            ring: FloatParam::new(&ring_name, 0.25, FloatRange::Linear { min: 0.0, max: 1.0 }),
+
+            gated_send: BoolParam::new(&gated_send_name, false),
+
+            scale: IntParam::new(
+                &scale_name,
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: SCALES.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|v| {
+                SCALES
+                    .get(v as usize)
+                    .map_or_else(String::new, |name| name.to_string())
+            })),
+
+            vel_floor: FloatParam::new(
+                &vel_floor_name,
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            ),
+
+            vel_ceiling: FloatParam::new(
+                &vel_ceiling_name,
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            ),
+
+            attack_noise: FloatParam::new(
+                &attack_noise_name,
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            ),
+
+            decay_link: FloatParam::new(
+                &decay_link_name,
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            ),
+
+            phase_random: FloatParam::new(
+                &phase_random_name,
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            ),
+
+            wide_tone: BoolParam::new(&wide_tone_name, false),
+
+            comp_bypass: BoolParam::new(&comp_bypass_name, false),
+
+            body_drive: FloatParam::new(
+                &body_drive_name,
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                },
+            ),
+
+            click: FloatParam::new(
+                &click_name,
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                },
+            ),
+
+            probability: FloatParam::new(
+                &probability_name,
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            ),
+
+            stereo: FloatParam::new(&stereo_name, 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+
+            max_pitch: FloatParam::new(
+                &max_pitch_name,
+                PITCH_MAX_HZ,
+                FloatRange::Skewed {
+                    min: 100.0,
+                    max: PITCH_MAX_HZ,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" Hz"),
         }
     }
 
@@ -214,7 +967,47 @@ impl DrumSlotParams {
     }
 }
 
+const MAX_SEED: i32 = 9999;
+
+/// Stable per-label default seed (FNV-1a), so slots start out decorrelated.
+fn default_seed(label: &str) -> i32 {
+    let hash = label.bytes().fold(0x811c_9dc5u32, |h, b| {
+        (h ^ b as u32).wrapping_mul(0x0100_0193)
+    });
+    (hash % MAX_SEED as u32) as i32 + 1
+}
+
 impl MasterParams {
+    /// Advance every smoother by `samples`, for blocks that skip rendering.
+    fn skip_smoothers(&self, samples: u32) {
+        self.drive.smoothed.next_step(samples);
+        self.comp.smoothed.next_step(samples);
+        self.reverb.smoothed.next_step(samples);
+        self.kit_pitch.smoothed.next_step(samples);
+        self.velocity_curve.smoothed.next_step(samples);
+        self.tightness.smoothed.next_step(samples);
+    }
+
+    /// Glide the master macros towards `v` (see `MasterValues` for the order).
+    pub fn glide_to(&self, v: &MasterValues, sample_rate: f32) {
+        self.drive.smoothed.set_target(sample_rate, v[0]);
+        self.comp.smoothed.set_target(sample_rate, v[1]);
+        self.reverb.smoothed.set_target(sample_rate, v[2]);
+        self.kit_pitch.smoothed.set_target(sample_rate, v[3]);
+        self.velocity_curve.smoothed.set_target(sample_rate, v[4]);
+    }
+
+    /// The master macro params, in `MasterValues` order.
+    pub fn macros(&self) -> [&FloatParam; MASTER_MACROS] {
+        [
+            &self.drive,
+            &self.comp,
+            &self.reverb,
+            &self.kit_pitch,
+            &self.velocity_curve,
+        ]
+    }
+
     pub fn from_values(
         drive: f32,
         comp: f32,
@@ -223,9 +1016,12 @@ impl MasterParams {
         velocity_curve: f32,
     ) -> Self {
         Self {
-            drive: FloatParam::new("Drive", drive, FloatRange::Linear { min: 0.0, max: 1.0 }),
-            comp: FloatParam::new("Comp", comp, FloatRange::Linear { min: 0.0, max: 1.0 }),
-            reverb: FloatParam::new("Reverb", reverb, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            drive: FloatParam::new("Drive", drive, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(20.0)),
+            comp: FloatParam::new("Comp", comp, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(20.0)),
+            reverb: FloatParam::new("Reverb", reverb, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(20.0)),
             kit_pitch: FloatParam::new(
                 "Kit Pitch",
                 kit_pitch,
@@ -234,12 +1030,251 @@ impl MasterParams {
                     max: 12.0,
                 },
             )
-            .with_unit("st"),
+            .with_unit("st")
+            .with_smoother(SmoothingStyle::Linear(20.0)),
             velocity_curve: FloatParam::new(
                 "Velocity",
                 velocity_curve,
                 FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(20.0)),
+            dither: BoolParam::new("Dither", false),
+            send_follows_level: BoolParam::new("Send Follows Level", true),
+            send_follows_decay: BoolParam::new("Send Follows Decay", false),
+            kit: IntParam::new(
+                "Kit",
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: FACTORY_KITS.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|v| {
+                FACTORY_KITS
+                    .get(v as usize)
+                    .map_or_else(String::new, |k| k.name.to_string())
+            })),
+            comp_auto_release: BoolParam::new("Comp Auto Release", false),
+            comp_mix: FloatParam::new("Parallel", 1.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            comp_attack: FloatParam::new(
+                "Comp Attack",
+                COMP_ATTACK_MS,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 100.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms"),
+            comp_release: FloatParam::new(
+                "Comp Release",
+                COMP_RELEASE_MS,
+                FloatRange::Skewed {
+                    min: 10.0,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-1.5),
+                },
+            )
+            .with_unit(" ms"),
+            bass_mono: FloatParam::new(
+                "Bass Mono",
+                0.0,
+                FloatRange::Skewed {
+                    min: 0.0,
+                    max: 300.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" Hz"),
+            air: FloatParam::new("Air", 0.0, FloatRange::Linear { min: 0.0, max: 9.0 })
+                .with_unit(" dB"),
+            reverb_pattern: IntParam::new(
+                "Reverb Room",
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: ER_PATTERNS.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|v| {
+                ER_PATTERNS
+                    .get(v as usize)
+                    .map_or_else(String::new, |p| p.name.to_string())
+            })),
+            reverb_density: IntParam::new(
+                "Reverb Density",
+                2,
+                IntRange::Linear {
+                    min: 1,
+                    max: FDN_MAX_LINES as i32,
+                },
             ),
+            reverb_decay: FloatParam::new(
+                "Reverb Decay",
+                0.25,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 4.0,
+                    factor: FloatRange::skew_factor(-1.5),
+                },
+            )
+            .with_unit(" s"),
+            reverb_hp: FloatParam::new(
+                "Reverb HP",
+                PRE_VERB_HP_MIN,
+                FloatRange::Skewed {
+                    min: PRE_VERB_HP_MIN,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" Hz"),
+            reverb_lp: FloatParam::new(
+                "Reverb LP",
+                PRE_VERB_LP_MAX,
+                FloatRange::Skewed {
+                    min: 1000.0,
+                    max: PRE_VERB_LP_MAX,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" Hz"),
+            cut_tail_on_stop: BoolParam::new("Cut Tail on Stop", false),
+            gentle_noise: BoolParam::new("Gentle Noise", false),
+            tightness: FloatParam::new(
+                "Tightness",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(20.0)),
+            sum_guard: BoolParam::new("Sum Guard", false),
+            humanize_timing: FloatParam::new(
+                "Humanize Timing",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            ),
+            timing_live_only: BoolParam::new("Timing Humanize Live Only", false),
+            invert_phase: BoolParam::new("Invert Phase", false),
+            comp_dual_mono: BoolParam::new("Comp Dual Mono", false),
+            center_comp: BoolParam::new("Center Compensation", false),
+            drive_model: IntParam::new(
+                "Drive Model",
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: DRIVE_MODELS.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|v| {
+                DRIVE_MODELS
+                    .get(v as usize)
+                    .map_or_else(String::new, |name| name.to_string())
+            })),
+            stereoize: FloatParam::new("Stereoize", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            relative_tails: BoolParam::new("Level-Relative Tails", false),
+            pan_law: IntParam::new(
+                "Pan Law",
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: PAN_LAWS.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|v| {
+                PAN_LAWS
+                    .get(v as usize)
+                    .map_or_else(String::new, |name| name.to_string())
+            })),
+            sc_listen: BoolParam::new("SC Listen", false),
+            mono_monitor: BoolParam::new("Mono Monitor", false),
+            tuning: FloatParam::new(
+                "Tuning",
+                440.0,
+                FloatRange::Linear {
+                    min: 415.0,
+                    max: 466.0,
+                },
+            )
+            .with_unit(" Hz"),
+            eco: BoolParam::new("Eco", false),
+            humanize_dist: IntParam::new(
+                "Humanize Distribution",
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: HUMANIZE_DISTS.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|v| {
+                HUMANIZE_DISTS
+                    .get(v as usize)
+                    .map_or_else(String::new, |name| name.to_string())
+            })),
+            lfo_rate: IntParam::new(
+                "LFO Rate",
+                2,
+                IntRange::Linear {
+                    min: 0,
+                    max: LFO_RATES.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|v| {
+                LFO_RATES
+                    .get(v as usize)
+                    .map_or_else(String::new, |name| name.to_string())
+            })),
+            lfo_depth: FloatParam::new("LFO Depth", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            lfo_dest: IntParam::new(
+                "LFO Destination",
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: LFO_DESTINATIONS.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|v| {
+                LFO_DESTINATIONS
+                    .get(v as usize)
+                    .map_or_else(String::new, |name| name.to_string())
+            })),
+            note_priority: IntParam::new(
+                "Note Priority",
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: NOTE_PRIORITIES.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|v| {
+                NOTE_PRIORITIES
+                    .get(v as usize)
+                    .map_or_else(String::new, |name| name.to_string())
+            })),
+            balance: FloatParam::new(
+                "Balance",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                },
+            ),
+            freeze_humanize: BoolParam::new("Freeze Humanize", false),
+            meter_mode: IntParam::new(
+                "Meter",
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: METER_MODES.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|v| {
+                METER_MODES
+                    .get(v as usize)
+                    .map_or_else(String::new, |name| name.to_string())
+            })),
         }
     }
 }
@@ -249,3 +1284,17 @@ impl Default for MasterParams {
         Self::from_values(0.1, 0.3, 0.2, 0.0, 0.5)
     }
 }
+
+/// Settle every smoother on its param's value, as the wrapper does on activation.
+#[cfg(test)]
+pub(crate) fn settle_smoothers(params: &impl Params) {
+    for (_, ptr, _) in params.param_map() {
+        unsafe {
+            match ptr {
+                ParamPtr::FloatParam(p) => (*p).smoothed.reset((*p).value()),
+                ParamPtr::IntParam(p) => (*p).smoothed.reset((*p).value()),
+                _ => {}
+            }
+        }
+    }
+}